@@ -0,0 +1,253 @@
+//! Derive macro for declaring `argparse` options on a structure
+//!
+//! ```ignore
+//! #[derive(Declare)]
+//! #[argparse(description = "Greet somebody.")]
+//! struct Options {
+//!     #[argparse(option("-v", "--verbose"), action = StoreTrue,
+//!                help = "Be verbose")]
+//!     verbose: bool,
+//!     #[argparse(option("--name"), help = "Name for the greeting")]
+//!     #[argparse(envvar = "GREETING_NAME")]
+//!     name: String,
+//! }
+//! ```
+//!
+//! Generates `argparse::Declare` implementation which calls `ap.refer(...)`
+//! for each annotated field, exactly like a hand-written parser would.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, parse_macro_input, Token};
+use syn::{Attribute, Data, DeriveInput, Expr, Fields, LitStr};
+
+
+/// A single `add_option` or `add_argument` call
+struct Declaration {
+    option: Vec<LitStr>,
+    argument: Option<LitStr>,
+    action: Option<Expr>,
+    help: Option<LitStr>,
+}
+
+#[derive(Default)]
+struct FieldSpec {
+    declarations: Vec<Declaration>,
+    metavar: Option<LitStr>,
+    envvars: Vec<LitStr>,
+    config_key: Option<LitStr>,
+    constraints: Vec<(&'static str, LitStr)>,
+    required: bool,
+    show_default: bool,
+    flatten: bool,
+}
+
+fn parse_field(attrs: &[Attribute]) -> syn::Result<FieldSpec> {
+    let mut spec = FieldSpec::default();
+    for attr in attrs {
+        if !attr.path().is_ident("argparse") {
+            continue;
+        }
+        let mut decl = Declaration {
+            option: Vec::new(),
+            argument: None,
+            action: None,
+            help: None,
+        };
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("option") {
+                let content;
+                parenthesized!(content in meta.input);
+                let names = Punctuated::<LitStr, Token![,]>
+                    ::parse_terminated(&content)?;
+                decl.option.extend(names);
+            } else if meta.path.is_ident("argument") {
+                decl.argument = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("action") {
+                decl.action = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("help") {
+                decl.help = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("metavar") {
+                spec.metavar = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("envvar") {
+                spec.envvars.push(meta.value()?.parse()?);
+            } else if meta.path.is_ident("config_key") {
+                spec.config_key = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("requires") {
+                spec.constraints.push(("requires", meta.value()?.parse()?));
+            } else if meta.path.is_ident("conflicts_with") {
+                spec.constraints.push(
+                    ("conflicts_with", meta.value()?.parse()?));
+            } else if meta.path.is_ident("required_if") {
+                spec.constraints.push(
+                    ("required_if", meta.value()?.parse()?));
+            } else if meta.path.is_ident("required") {
+                spec.required = true;
+            } else if meta.path.is_ident("show_default") {
+                spec.show_default = true;
+            } else if meta.path.is_ident("flatten") {
+                spec.flatten = true;
+            } else {
+                return Err(meta.error("unknown argparse attribute"));
+            }
+            Ok(())
+        })?;
+        if !decl.option.is_empty() && decl.argument.is_some() {
+            return Err(syn::Error::new_spanned(attr,
+                "`option(...)` and `argument` must be in separate attributes"));
+        }
+        if !decl.option.is_empty() || decl.argument.is_some() {
+            spec.declarations.push(decl);
+        } else if decl.action.is_some() || decl.help.is_some() {
+            return Err(syn::Error::new_spanned(attr,
+                "either `option(...)` or `argument = \"...\"` is required"));
+        }
+    }
+    Ok(spec)
+}
+
+fn parse_description(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut description = None;
+    for attr in attrs {
+        if !attr.path().is_ident("argparse") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("description") {
+                description = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown argparse attribute"))
+            }
+        })?;
+    }
+    Ok(description)
+}
+
+fn declare_field(member: TokenStream2, spec: &FieldSpec) -> TokenStream2 {
+    if spec.flatten {
+        return quote! {
+            ::argparse::Declare::declare(&mut self.#member, ap);
+        };
+    }
+    let mut calls = Vec::new();
+    for decl in &spec.declarations {
+        let action = match decl.action {
+            Some(ref action) => quote! { #action },
+            None => quote! { ::argparse::Store },
+        };
+        let help = match decl.help {
+            Some(ref help) => quote! { #help },
+            None => quote! { "" },
+        };
+        if let Some(ref name) = decl.argument {
+            calls.push(quote! { .add_argument(#name, #action, #help) });
+        } else {
+            let names = &decl.option;
+            calls.push(quote! { .add_option(&[#(#names),*], #action, #help) });
+        }
+    }
+    if let Some(ref metavar) = spec.metavar {
+        calls.push(quote! { .metavar(#metavar) });
+    }
+    for envvar in &spec.envvars {
+        calls.push(quote! { .envvar(#envvar) });
+    }
+    if let Some(ref key) = spec.config_key {
+        calls.push(quote! { .config_key(#key, ::argparse::Store) });
+    }
+    if spec.required {
+        calls.push(quote! { .required() });
+    }
+    if spec.show_default {
+        calls.push(quote! { .show_default() });
+    }
+    for &(method, ref name) in &spec.constraints {
+        let method = syn::Ident::new(method, name.span());
+        calls.push(quote! { .#method(#name) });
+    }
+    quote! {
+        ap.refer(&mut self.#member) #(#calls)*;
+    }
+}
+
+fn derive(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(&input.ident,
+                "Declare can only be derived for structs"));
+        }
+    };
+    let mut body = Vec::new();
+    if let Some(description) = parse_description(&input.attrs)? {
+        body.push(quote! { ap.set_description(#description); });
+    }
+    match *fields {
+        Fields::Named(ref fields) => {
+            for field in &fields.named {
+                let spec = parse_field(&field.attrs)?;
+                if spec.declarations.is_empty() && !spec.flatten {
+                    continue;
+                }
+                let ident = field.ident.as_ref().unwrap();
+                body.push(declare_field(quote! { #ident }, &spec));
+            }
+        }
+        Fields::Unnamed(ref fields) => {
+            for (idx, field) in fields.unnamed.iter().enumerate() {
+                let spec = parse_field(&field.attrs)?;
+                if spec.declarations.is_empty() && !spec.flatten {
+                    continue;
+                }
+                let index = syn::Index::from(idx);
+                body.push(declare_field(quote! { #index }, &spec));
+            }
+        }
+        Fields::Unit => {}
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::argparse::Declare for #name #ty_generics
+            #where_clause
+        {
+            fn declare<'parser>(&'parser mut self,
+                ap: &mut ::argparse::ArgumentParser<'parser>)
+            {
+                #(#body)*
+            }
+        }
+    })
+}
+
+/// Derive `argparse::Declare` from `#[argparse(...)]` attributes
+///
+/// Field attributes:
+///
+/// * `option("-v", "--verbose")` -- add an option with these names
+/// * `argument = "name"` -- add a positional argument
+/// * `action = StoreTrue` -- action for the option or argument (any
+///   expression, `Store` by default)
+/// * `help = "..."` -- help for the option or argument
+/// * `metavar = "NAME"`, `envvar = "VAR"`, `config_key = "key"`,
+///   `required`, `show_default` -- same as methods of `argparse::Ref`
+/// * `requires = "--opt"`, `conflicts_with = "--opt"`,
+///   `required_if = "--opt"` -- same as methods of `argparse::Ref`
+/// * `flatten` -- the field implements `Declare` itself
+///
+/// Each attribute having `option` or `argument` adds one more
+/// option (argument) for the field. The struct attribute
+/// `description = "..."` sets description of the parser.
+#[proc_macro_derive(Declare, attributes(argparse))]
+pub fn derive_declare(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match derive(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
@@ -0,0 +1,121 @@
+extern crate argparse;
+extern crate argparse_derive;
+
+use std::path::PathBuf;
+
+use argparse::{ArgumentParser, Declare, ParseError};
+use argparse::{Store, StoreTrue, Collect, IncrBy, Parse};
+use argparse_derive::Declare;
+
+
+#[derive(Declare)]
+#[argparse(description = "Test program")]
+struct Options {
+    #[argparse(option("-v", "--verbose"), action = IncrBy(1u32),
+               help = "Be verbose")]
+    verbose: u32,
+    #[argparse(option("--name"), help = "Name for the greeting")]
+    #[argparse(metavar = "NAME", envvar = "TEST_DERIVE_NAME")]
+    name: String,
+    #[argparse(option("-o", "--output"), action = Parse, required)]
+    #[argparse(conflicts_with = "--name")]
+    output: PathBuf,
+    #[argparse(argument = "items", action = Collect, help = "Items")]
+    items: Vec<u32>,
+    #[argparse(flatten)]
+    common: Common,
+    not_an_option: bool,
+}
+
+#[derive(Declare)]
+struct Common {
+    #[argparse(option("-q", "--quiet"), action = StoreTrue)]
+    #[argparse(option("--silent"), action = StoreTrue,
+               help = "Same as --quiet")]
+    quiet: bool,
+}
+
+fn options() -> Options {
+    return Options {
+        verbose: 0,
+        name: "World".to_string(),
+        output: PathBuf::new(),
+        items: Vec::new(),
+        common: Common { quiet: false },
+        not_an_option: false,
+    };
+}
+
+fn parse(opts: &mut Options, args: &[&str]) -> Result<(), ParseError> {
+    let mut ap = ArgumentParser::new();
+    opts.declare(&mut ap);
+    return ap.try_parse(args.iter().map(|x| x.to_string()).collect(),
+        &mut Vec::new(), &mut Vec::new());
+}
+
+#[test]
+fn test_parse() {
+    let mut opts = options();
+    parse(&mut opts, &["./argparse_test", "-vv",
+        "-o", "out.txt", "--silent", "1", "2"]).unwrap();
+    assert_eq!(opts.verbose, 2);
+    assert_eq!(opts.name, "World");
+    assert_eq!(opts.output, PathBuf::from("out.txt"));
+    assert_eq!(opts.items, vec![1, 2]);
+    assert!(opts.common.quiet);
+    assert!(!opts.not_an_option);
+}
+
+#[test]
+fn test_conflict() {
+    let mut opts = options();
+    assert_eq!(parse(&mut opts, &["./argparse_test", "-o", "x", "--name=y"]),
+        Err(ParseError::ConflictingOptions {
+            option: "-o".to_string(),
+            other: "--name".to_string(),
+        }));
+}
+
+#[test]
+fn test_required() {
+    let mut opts = options();
+    assert_eq!(parse(&mut opts, &["./argparse_test"]),
+        Err(ParseError::MissingOption(
+            vec!["-o".to_string(), "--output".to_string()])));
+}
+
+#[test]
+fn test_help_same_as_manual() {
+    let mut derived = options();
+    let mut derived_help = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        derived.declare(&mut ap);
+        ap.print_help("./argparse_test", &mut derived_help).unwrap();
+    }
+
+    let mut opts = options();
+    let mut manual_help = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Test program");
+        ap.refer(&mut opts.verbose)
+            .add_option(&["-v", "--verbose"], IncrBy(1u32), "Be verbose");
+        ap.refer(&mut opts.name)
+            .add_option(&["--name"], Store, "Name for the greeting")
+            .metavar("NAME")
+            .envvar("TEST_DERIVE_NAME");
+        ap.refer(&mut opts.output)
+            .add_option(&["-o", "--output"], Parse, "")
+            .required()
+            .conflicts_with("--name");
+        ap.refer(&mut opts.items)
+            .add_argument("items", Collect, "Items");
+        ap.refer(&mut opts.common.quiet)
+            .add_option(&["-q", "--quiet"], StoreTrue, "")
+            .add_option(&["--silent"], StoreTrue, "Same as --quiet");
+        ap.print_help("./argparse_test", &mut manual_help).unwrap();
+    }
+    assert_eq!(String::from_utf8(derived_help).unwrap(),
+               String::from_utf8(manual_help).unwrap());
+}
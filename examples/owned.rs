@@ -0,0 +1,34 @@
+extern crate argparse;
+
+use argparse::{OwnedParser, Handle, StoreTrue, Store};
+
+struct Options {
+    parser: OwnedParser,
+    verbose: Handle<bool>,
+    name: Handle<String>,
+}
+
+fn options() -> Options {
+    let mut parser = OwnedParser::new();
+    parser.set_description("Greet somebody.");
+    let verbose = parser.add_var(false)
+        .add_option(&["-v", "--verbose"], StoreTrue,
+        "Be verbose")
+        .handle();
+    let name = parser.add_var("World".to_string())
+        .add_option(&["--name"], Store,
+        "Name for the greeting")
+        .handle();
+    return Options { parser: parser, verbose: verbose, name: name };
+}
+
+fn main() {
+    let options = options();
+    let mut values = options.parser.parse_args_or_exit();
+    let name = values.take(&options.name);
+
+    if *values.get(&options.verbose) {
+        println!("name is {}", name);
+    }
+    println!("Hello {}!", name);
+}
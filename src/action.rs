@@ -1,23 +1,106 @@
 use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::fmt;
+use std::ops::{RangeInclusive, RangeFrom};
 use std::rc::Rc;
+use std::usize;
+
+use completion::Completion;
 
 pub enum ParseResult {
     Parsed,
     Help,
     Exit,
     Error(String),
+    /// Value can't be parsed: the raw value and the reason (if known)
+    BadValue(String, Option<String>),
+    /// Wrong number of values: the expected arity and the number given
+    WrongNumber(Arity, usize),
 }
 
 
 pub enum Action<'a> {
     Flag(Box<IFlagAction + 'a>),
     Single(Box<IArgAction + 'a>),
+    /// Takes a value if there is one, otherwise runs the flag action
+    Optional(Box<IArgAction + 'a>, Box<IFlagAction + 'a>),
     Push(Box<IArgsAction + 'a>),
     Many(Box<IArgsAction + 'a>),
 }
 
+/// Number of values taken by a list option or positional argument
+///
+/// Created from a number (exactly `n` values), an inclusive range
+/// (`1..=3`) or an open range (`2..`). See `Ref::nargs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    /// Inclusive, `usize::MAX` means unlimited
+    pub max: usize,
+}
+
+impl From<usize> for Arity {
+    fn from(num: usize) -> Arity {
+        return Arity { min: num, max: num };
+    }
+}
+
+impl From<RangeInclusive<usize>> for Arity {
+    fn from(range: RangeInclusive<usize>) -> Arity {
+        return Arity { min: *range.start(), max: *range.end() };
+    }
+}
+
+impl From<RangeFrom<usize>> for Arity {
+    fn from(range: RangeFrom<usize>) -> Arity {
+        return Arity { min: range.start, max: usize::MAX };
+    }
+}
+
+impl Arity {
+    /// Whether the number of values is acceptable
+    pub fn contains(&self, num: usize) -> bool {
+        return num >= self.min && num <= self.max;
+    }
+
+    /// Whether the values may be split into groups of acceptable size,
+    /// where no groups at all is fine too (used for collections)
+    pub(crate) fn contains_groups(&self, num: usize) -> bool {
+        if num == 0 || self.min == 0 {
+            return true;
+        }
+        let groups = num / self.min;
+        return groups > 0 && groups.saturating_mul(self.max) >= num;
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = if self.max == 1 { "value" } else { "values" };
+        if self.min == self.max {
+            return write!(f, "{} {}", self.min, plural);
+        } else if self.max == usize::MAX {
+            let plural = if self.min == 1 { "value" } else { "values" };
+            return write!(f, "at least {} {}", self.min, plural);
+        } else {
+            return write!(f, "{} to {} {}", self.min, self.max, plural);
+        }
+    }
+}
+
 pub trait TypedAction<T> {
     fn bind<'x>(&self, Rc<RefCell<&'x mut T>>) -> Action<'x>;
+    /// What shell completion should offer for the value of the action
+    fn completion(&self) -> Completion {
+        return Completion::Default;
+    }
+    /// The number of values the action takes, if it's fixed
+    ///
+    /// Used unless `Ref::nargs` is set, e.g. `Parse` into a pair takes
+    /// two values
+    fn arity(&self) -> Option<Arity> {
+        return None;
+    }
 }
 
 pub trait IFlagAction {
@@ -26,8 +109,35 @@ pub trait IFlagAction {
 
 pub trait IArgAction {
     fn parse_arg(&self, arg: &str) -> ParseResult;
+    /// Parse the value which may be not valid UTF-8
+    ///
+    /// By default fails on invalid UTF-8 and calls `parse_arg` otherwise
+    fn parse_os_arg(&self, arg: &OsStr) -> ParseResult {
+        match arg.to_str() {
+            Some(arg) => return self.parse_arg(arg),
+            None => return invalid_utf8(arg),
+        }
+    }
 }
 
 pub trait IArgsAction {
     fn parse_args(&self, args: &[&str]) -> ParseResult;
+    /// Parse the values which may be not valid UTF-8
+    ///
+    /// By default fails on invalid UTF-8 and calls `parse_args` otherwise
+    fn parse_os_args(&self, args: &[&OsStr]) -> ParseResult {
+        let mut strings = Vec::with_capacity(args.len());
+        for arg in args.iter() {
+            match arg.to_str() {
+                Some(arg) => strings.push(arg),
+                None => return invalid_utf8(arg),
+            }
+        }
+        return self.parse_args(&strings[..]);
+    }
+}
+
+fn invalid_utf8(arg: &OsStr) -> ParseResult {
+    return ParseResult::BadValue(arg.to_string_lossy().into_owned(),
+        Some("invalid utf-8".to_string()));
 }
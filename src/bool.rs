@@ -20,3 +20,14 @@ impl TypedAction<bool> for StoreFalse {
     }
 }
 
+
+/// Canonical form of a boolean environment variable or configuration value
+///
+/// Accepts `1/0`, `yes/no`, `on/off` and `true/false` in any case
+pub fn normalize(value: &str) -> Option<&'static str> {
+    match &value.trim().to_ascii_lowercase()[..] {
+        "1" | "yes" | "on" | "true" => return Some("true"),
+        "0" | "no" | "off" | "false" => return Some("false"),
+        _ => return None,
+    }
+}
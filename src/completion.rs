@@ -0,0 +1,419 @@
+use std::io::Write;
+use std::io::Result as IoResult;
+use std::str::FromStr;
+
+use action::Action::{Flag, Optional, Push};
+use parser::{ArgumentParser, GenericOption};
+
+
+/// What shell completion should offer as a value of an option or argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completion {
+    /// Nothing specific, let the shell decide (usually completes files)
+    Default,
+    /// File names
+    Files,
+    /// Directory names
+    Directories,
+    /// Fixed list of values, useful for enums
+    Values(Vec<String>),
+}
+
+/// Shell to generate completion script for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = ();
+    fn from_str(src: &str) -> Result<Shell, ()> {
+        return match src {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(()),
+        };
+    }
+}
+
+fn identifier(name: &str) -> String {
+    return name.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+}
+
+fn quote(text: &str) -> String {
+    return format!("'{}'", text.replace("'", r"'\''"));
+}
+
+fn zsh_escape(text: &str) -> String {
+    return text
+        .replace("\\", "\\\\")
+        .replace("'", r"'\''")
+        .replace("[", "\\[")
+        .replace("]", "\\]")
+        .replace(":", "\\:")
+        .replace("$", "\\$")
+        .replace("`", "\\`")
+        .replace("\n", " ");
+}
+
+fn fish_quote(text: &str) -> String {
+    return format!("'{}'", text
+        .replace("\\", "\\\\")
+        .replace("'", "\\'")
+        .replace("\n", " "));
+}
+
+fn takes_value(opt: &GenericOption) -> bool {
+    match opt.action {
+        Flag(_) => false,
+        _ => true,
+    }
+}
+
+pub struct CompletionFormatter<'a> {
+    name: &'a str,
+    buf: &'a mut (Write + 'a),
+}
+
+impl<'a> CompletionFormatter<'a> {
+    pub fn print_completion(parser: &ArgumentParser, shell: Shell,
+        name: &str, writer: &mut Write)
+        -> IoResult<()>
+    {
+        let mut fmt = CompletionFormatter { name: name, buf: writer };
+        return match shell {
+            Shell::Bash => fmt.write_bash(parser),
+            Shell::Zsh => fmt.write_zsh(parser),
+            Shell::Fish => fmt.write_fish(parser),
+        };
+    }
+
+    fn bash_reply(&mut self, completion: &Completion) -> IoResult<()> {
+        match *completion {
+            Completion::Default => {
+                try!(write!(self.buf, "COMPREPLY=()"));
+            }
+            Completion::Files => {
+                try!(write!(self.buf,
+                    "COMPREPLY=($(compgen -f -- \"$cur\"))"));
+            }
+            Completion::Directories => {
+                try!(write!(self.buf,
+                    "COMPREPLY=($(compgen -d -- \"$cur\"))"));
+            }
+            Completion::Values(ref values) => {
+                try!(write!(self.buf,
+                    "COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+                    quote(&values.join(" "))));
+            }
+        }
+        return Ok(());
+    }
+
+    fn write_bash_commands(&mut self, parser: &ArgumentParser, ident: &str)
+        -> IoResult<()>
+    {
+        for sub in parser.subcommands.iter() {
+            let child = format!("{}_{}", ident, identifier(sub.name));
+            try!(writeln!(self.buf, "            {})  cmd={} ;;",
+                quote(&format!("{}:{}", ident, sub.name)), child));
+            try!(self.write_bash_commands(&sub.parser, &child));
+        }
+        return Ok(());
+    }
+
+    fn write_bash_parser(&mut self, parser: &ArgumentParser, ident: &str)
+        -> IoResult<()>
+    {
+        try!(writeln!(self.buf, "        {})", ident));
+        try!(writeln!(self.buf, "            case \"$prev\" in"));
+        for opt in parser.options.iter() {
+            if !takes_value(opt) {
+                continue;
+            }
+            let var = &parser.vars[opt.varid.unwrap()];
+            try!(write!(self.buf, "                {})\n                    ",
+                opt.names.join("|")));
+            try!(self.bash_reply(&var.completion));
+            try!(writeln!(self.buf, "\n                    return ;;"));
+        }
+        try!(writeln!(self.buf, "            esac"));
+        let mut names = Vec::new();
+        for opt in parser.options.iter() {
+            names.extend(parser.completion_names(opt));
+        }
+        try!(writeln!(self.buf, "            if [[ \"$cur\" == -* ]]; then"));
+        try!(writeln!(self.buf,
+            "                COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+            quote(&names.join(" "))));
+        try!(writeln!(self.buf, "            else"));
+        try!(write!(self.buf, "                "));
+        if !parser.subcommands.is_empty() {
+            let commands: Vec<_> = parser.subcommands.iter()
+                .map(|sub| sub.name).collect();
+            try!(self.bash_reply(&Completion::Values(
+                commands.iter().map(|x| x.to_string()).collect())));
+        } else {
+            let completion = parser.arguments.iter()
+                .chain(parser.catchall_argument.iter())
+                .map(|arg| &parser.vars[arg.varid].completion)
+                .find(|c| **c != Completion::Default)
+                .unwrap_or(&Completion::Default);
+            try!(self.bash_reply(completion));
+        }
+        try!(writeln!(self.buf, "\n            fi"));
+        try!(writeln!(self.buf, "            ;;"));
+        for sub in parser.subcommands.iter() {
+            let child = format!("{}_{}", ident, identifier(sub.name));
+            try!(self.write_bash_parser(&sub.parser, &child));
+        }
+        return Ok(());
+    }
+
+    fn write_bash(&mut self, parser: &ArgumentParser) -> IoResult<()> {
+        let ident = identifier(self.name);
+        try!(writeln!(self.buf, "_{}() {{", ident));
+        try!(writeln!(self.buf, "    local cur prev cmd i"));
+        try!(writeln!(self.buf, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\""));
+        try!(writeln!(self.buf,
+            "    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\""));
+        // Bash splits `--option=value` into three words
+        try!(writeln!(self.buf, "    if [[ \"$cur\" == \"=\" ]]; then"));
+        try!(writeln!(self.buf, "        cur=\"\""));
+        try!(writeln!(self.buf, "    elif [[ \"$prev\" == \"=\" ]]; then"));
+        try!(writeln!(self.buf,
+            "        prev=\"${{COMP_WORDS[COMP_CWORD-2]}}\""));
+        try!(writeln!(self.buf, "    fi"));
+        try!(writeln!(self.buf, "    cmd={}", ident));
+        try!(writeln!(self.buf, "    for ((i = 1; i < COMP_CWORD; i++)); do"));
+        try!(writeln!(self.buf,
+            "        case \"$cmd:${{COMP_WORDS[i]}}\" in"));
+        try!(self.write_bash_commands(parser, &ident));
+        try!(writeln!(self.buf, "        esac"));
+        try!(writeln!(self.buf, "    done"));
+        try!(writeln!(self.buf, "    case \"$cmd\" in"));
+        try!(self.write_bash_parser(parser, &ident));
+        try!(writeln!(self.buf, "    esac"));
+        try!(writeln!(self.buf, "}}"));
+        try!(writeln!(self.buf, "complete -o default -F _{} {}",
+            ident, quote(self.name)));
+        return Ok(());
+    }
+
+    fn zsh_action(completion: &Completion) -> String {
+        match *completion {
+            Completion::Default => "_default".to_string(),
+            Completion::Files => "_files".to_string(),
+            Completion::Directories => "_files -/".to_string(),
+            Completion::Values(ref values) => {
+                let values: Vec<_> = values.iter()
+                    .map(|x| zsh_escape(x)).collect();
+                format!("({})", values.join(" "))
+            }
+        }
+    }
+
+    fn write_zsh_parser(&mut self, parser: &ArgumentParser, ident: &str)
+        -> IoResult<()>
+    {
+        try!(writeln!(self.buf, "_{}() {{", ident));
+        try!(writeln!(self.buf, "    local context state state_descr line"));
+        try!(writeln!(self.buf, "    typeset -A opt_args"));
+        try!(write!(self.buf, "    _arguments -s -C"));
+        for opt in parser.options.iter() {
+            let visible = parser.completion_names(opt);
+            if visible.is_empty() {
+                continue;
+            }
+            let value = takes_value(opt);
+            let names: Vec<_> = visible.iter().map(|name| {
+                if !value {
+                    name.to_string()
+                } else if name.starts_with("--") {
+                    format!("{}=", name)
+                } else {
+                    format!("{}+", name)
+                }
+            }).collect();
+            try!(write!(self.buf, " \\\n        "));
+            match opt.action {
+                Push(_) => try!(write!(self.buf, "'*'")),
+                _ if names.len() > 1 => {
+                    try!(write!(self.buf, "'({})'", visible.join(" ")));
+                }
+                _ => {}
+            }
+            if names.len() > 1 {
+                try!(write!(self.buf, "{{{}}}", names.join(",")));
+            } else {
+                try!(write!(self.buf, "{}", names[0]));
+            }
+            try!(write!(self.buf, "'[{}]", zsh_escape(opt.help)));
+            if value {
+                let var = &parser.vars[opt.varid.unwrap()];
+                // a double colon marks the value as optional
+                let sep = match opt.action {
+                    Optional(..) => "::",
+                    _ => ":",
+                };
+                try!(write!(self.buf, "{}{}:{}", sep, zsh_escape(&var.metavar),
+                    CompletionFormatter::zsh_action(&var.completion)));
+            }
+            try!(write!(self.buf, "'"));
+        }
+        for (idx, arg) in parser.arguments.iter().enumerate() {
+            let var = &parser.vars[arg.varid];
+            try!(write!(self.buf, " \\\n        '{}:{}:{}'", idx + 1,
+                zsh_escape(arg.name),
+                CompletionFormatter::zsh_action(&var.completion)));
+        }
+        match parser.catchall_argument {
+            Some(ref arg) => {
+                let var = &parser.vars[arg.varid];
+                try!(write!(self.buf, " \\\n        '*:{}:{}'",
+                    zsh_escape(arg.name),
+                    CompletionFormatter::zsh_action(&var.completion)));
+            }
+            None => {}
+        }
+        if !parser.subcommands.is_empty() {
+            try!(write!(self.buf, " \\\n        ':command:->command'"));
+            try!(write!(self.buf, " \\\n        '*::arg:->args'"));
+        }
+        try!(writeln!(self.buf, ""));
+        if !parser.subcommands.is_empty() {
+            try!(writeln!(self.buf, "    case $state in"));
+            try!(writeln!(self.buf, "        (command)"));
+            try!(writeln!(self.buf, "            local -a commands"));
+            try!(writeln!(self.buf, "            commands=("));
+            for sub in parser.subcommands.iter() {
+                try!(writeln!(self.buf, "                '{}:{}'",
+                    zsh_escape(sub.name), zsh_escape(sub.help)));
+            }
+            try!(writeln!(self.buf, "            )"));
+            try!(writeln!(self.buf,
+                "            _describe -t commands 'command' commands"));
+            try!(writeln!(self.buf, "            ;;"));
+            try!(writeln!(self.buf, "        (args)"));
+            try!(writeln!(self.buf, "            case $words[1] in"));
+            for sub in parser.subcommands.iter() {
+                try!(writeln!(self.buf, "                ({}) _{}_{} ;;",
+                    sub.name, ident, identifier(sub.name)));
+            }
+            try!(writeln!(self.buf, "            esac"));
+            try!(writeln!(self.buf, "            ;;"));
+            try!(writeln!(self.buf, "    esac"));
+        }
+        try!(writeln!(self.buf, "}}"));
+        for sub in parser.subcommands.iter() {
+            try!(writeln!(self.buf, ""));
+            let child = format!("{}_{}", ident, identifier(sub.name));
+            try!(self.write_zsh_parser(&sub.parser, &child));
+        }
+        return Ok(());
+    }
+
+    fn write_zsh(&mut self, parser: &ArgumentParser) -> IoResult<()> {
+        let ident = identifier(self.name);
+        try!(writeln!(self.buf, "#compdef {}", self.name));
+        try!(writeln!(self.buf, ""));
+        try!(self.write_zsh_parser(parser, &ident));
+        try!(writeln!(self.buf, ""));
+        try!(writeln!(self.buf, "if [ \"$funcstack[1]\" = \"_{}\" ]; then",
+            ident));
+        try!(writeln!(self.buf, "    _{} \"$@\"", ident));
+        try!(writeln!(self.buf, "else"));
+        try!(writeln!(self.buf, "    compdef _{} {}", ident, self.name));
+        try!(writeln!(self.buf, "fi"));
+        return Ok(());
+    }
+
+    fn fish_values(completion: &Completion) -> String {
+        match *completion {
+            Completion::Default => "".to_string(),
+            Completion::Files => " -F".to_string(),
+            Completion::Directories => {
+                " -f -a '(__fish_complete_directories)'".to_string()
+            }
+            Completion::Values(ref values) => {
+                format!(" -f -a {}", fish_quote(&values.join(" ")))
+            }
+        }
+    }
+
+    fn write_fish_parser(&mut self, parser: &ArgumentParser,
+        path: &Vec<&str>)
+        -> IoResult<()>
+    {
+        let mut conditions: Vec<_> = path.iter()
+            .map(|cmd| format!("__fish_seen_subcommand_from {}", cmd))
+            .collect();
+        let commands: Vec<_> = parser.subcommands.iter()
+            .map(|sub| sub.name).collect();
+        if !commands.is_empty() {
+            if path.is_empty() {
+                conditions.push("__fish_use_subcommand".to_string());
+            } else {
+                conditions.push(format!("not __fish_seen_subcommand_from {}",
+                    commands.join(" ")));
+            }
+        }
+        let cond = if conditions.is_empty() {
+            "".to_string()
+        } else {
+            format!(" -n {}", fish_quote(&conditions.join("; and ")))
+        };
+        for opt in parser.options.iter() {
+            let names = parser.completion_names(opt);
+            if names.is_empty() {
+                continue;
+            }
+            try!(write!(self.buf, "complete -c {}{}", self.name, cond));
+            for name in names.iter() {
+                if name.starts_with("--") {
+                    try!(write!(self.buf, " -l {}", &name[2..]));
+                } else {
+                    try!(write!(self.buf, " -s {}", &name[1..]));
+                }
+            }
+            if takes_value(opt) {
+                let var = &parser.vars[opt.varid.unwrap()];
+                try!(write!(self.buf, " -r{}",
+                    CompletionFormatter::fish_values(&var.completion)));
+            }
+            try!(writeln!(self.buf, " -d {}", fish_quote(opt.help)));
+        }
+        for sub in parser.subcommands.iter() {
+            try!(writeln!(self.buf, "complete -c {}{} -f -a {} -d {}",
+                self.name, cond, fish_quote(sub.name), fish_quote(sub.help)));
+        }
+        for arg in parser.arguments.iter()
+            .chain(parser.catchall_argument.iter())
+        {
+            let var = &parser.vars[arg.varid];
+            match var.completion {
+                Completion::Default => {}
+                ref completion => {
+                    try!(writeln!(self.buf, "complete -c {}{}{}",
+                        self.name, cond,
+                        CompletionFormatter::fish_values(completion)));
+                }
+            }
+        }
+        for sub in parser.subcommands.iter() {
+            let mut child = path.clone();
+            child.push(sub.name);
+            try!(self.write_fish_parser(&sub.parser, &child));
+        }
+        return Ok(());
+    }
+
+    fn write_fish(&mut self, parser: &ArgumentParser) -> IoResult<()> {
+        return self.write_fish_parser(parser, &Vec::new());
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+
+/// Error reading or parsing a configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Path of the file (if read from file)
+    pub path: Option<String>,
+    /// Line number where the error is (if known)
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path {
+            Some(ref path) => try!(write!(f, "{}", path)),
+            None => try!(write!(f, "<config>")),
+        }
+        match self.line {
+            Some(line) => try!(write!(f, ":{}", line)),
+            None => {}
+        }
+        return write!(f, ": {}", self.message);
+    }
+}
+
+impl Error for ConfigError {}
+
+struct Value {
+    value: String,
+    line: Option<usize>,
+}
+
+/// Key/value configuration source
+///
+/// The file format is a subset of INI and TOML:
+///
+/// ```ini
+/// # comment
+/// verbose = true
+/// name = "John \"Doe\""
+///
+/// [server]
+/// port = 8080      # key is "server.port"
+/// ```
+///
+/// Values may be bare (up to the `#` comment), or quoted with double
+/// quotes (supporting `\"`, `\\`, `\n` and `\t` escapes) or single quotes
+/// (literal). Values are parsed by the actions like values of the
+/// environment variables, see `Ref::config_key`.
+pub struct Config {
+    path: Option<String>,
+    values: HashMap<String, Value>,
+}
+
+fn parse_quoted(text: &str, quote: char) -> Result<(String, &str), String> {
+    let mut result = String::new();
+    let mut chars = text.char_indices();
+    chars.next();  // opening quote
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some((idx, ch)) if ch == quote => {
+                return Ok((result, &text[idx+1..]));
+            }
+            Some((_, '\\')) if quote == '"' => {
+                match chars.next() {
+                    Some((_, '"')) => result.push('"'),
+                    Some((_, '\\')) => result.push('\\'),
+                    Some((_, 'n')) => result.push('\n'),
+                    Some((_, 't')) => result.push('\t'),
+                    Some((_, ch)) => {
+                        return Err(format!("unknown escape \\{}", ch));
+                    }
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+            Some((_, ch)) => result.push(ch),
+        }
+    }
+}
+
+fn parse_value(text: &str) -> Result<String, String> {
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (value, rest) = try!(parse_quoted(text,
+            text.chars().next().unwrap()));
+        let rest = rest.trim();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(format!("unexpected {:?} after the value", rest));
+        }
+        return Ok(value);
+    }
+    let value = match text.find('#') {
+        Some(idx) => &text[..idx],
+        None => text,
+    };
+    return Ok(value.trim().to_string());
+}
+
+impl Config {
+    /// Create an empty configuration
+    ///
+    /// Use `set` to fill it from any other source
+    pub fn new() -> Config {
+        return Config { path: None, values: HashMap::new() };
+    }
+
+    /// Parse configuration text
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        return Config::parse_text(text, None);
+    }
+
+    /// Read and parse configuration file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let name = path.to_string_lossy().into_owned();
+        let mut text = String::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| ConfigError {
+                path: Some(name.clone()),
+                line: None,
+                message: e.to_string(),
+            }));
+        return Config::parse_text(&text, Some(name));
+    }
+
+    fn parse_text(text: &str, path: Option<String>)
+        -> Result<Config, ConfigError>
+    {
+        let mut values = HashMap::new();
+        let mut section = String::new();
+        for (idx, line) in text.lines().enumerate() {
+            let lineno = idx + 1;
+            let error = |message: String| ConfigError {
+                path: path.clone(),
+                line: Some(lineno),
+                message: message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#')
+                || line.starts_with(';')
+            {
+                continue;
+            }
+            if line.starts_with('[') {
+                if !line.ends_with(']') {
+                    return Err(error("expected `]`".to_string()));
+                }
+                section = line[1..line.len()-1].trim().to_string();
+                if section.is_empty() {
+                    return Err(error("empty section name".to_string()));
+                }
+                continue;
+            }
+            let mut pair = line.splitn(2, '=');
+            let key = pair.next().unwrap().trim();
+            let value = match pair.next() {
+                Some(value) => {
+                    try!(parse_value(value.trim()).map_err(&error))
+                }
+                None => {
+                    return Err(error("expected `key = value`".to_string()));
+                }
+            };
+            if key.is_empty() {
+                return Err(error("empty key".to_string()));
+            }
+            let key = if section.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", section, key)
+            };
+            if values.contains_key(&key) {
+                return Err(error(format!("duplicate key {}", key)));
+            }
+            values.insert(key, Value {
+                value: value,
+                line: Some(lineno),
+            });
+        }
+        return Ok(Config { path: path, values: values });
+    }
+
+    /// Set the value, overriding the value read from file
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), Value {
+            value: value.to_string(),
+            line: None,
+        });
+    }
+
+    /// Get the raw value
+    pub fn get(&self, key: &str) -> Option<&str> {
+        return self.values.get(key).map(|v| &v.value[..]);
+    }
+
+    /// Location of the key for messages: `path:line: key`
+    pub(crate) fn location(&self, key: &str) -> String {
+        let mut result = match self.path {
+            Some(ref path) => path.clone(),
+            None => "<config>".to_string(),
+        };
+        match self.values.get(key).and_then(|v| v.line) {
+            Some(line) => result.push_str(&format!(":{}", line)),
+            None => {}
+        }
+        result.push_str(&format!(": {}", key));
+        return result;
+    }
+}
@@ -1,12 +1,16 @@
 use std::cell::RefCell;
+use std::ffi::OsStr;
 use std::rc::Rc;
 
 use super::{Parse, ParseOption, ParseList, ParseCollect, FromCommandLine};
+use super::ParseOrConst;
 use super::action::Action;
 use super::action::{TypedAction, IArgAction, IArgsAction};
 use super::action::ParseResult;
-use super::action::ParseResult::{Parsed, Error};
-use super::action::Action::{Single, Push, Many};
+use super::action::ParseResult::{Parsed, BadValue};
+use super::action::Action::{Single, Optional, Push, Many};
+use super::generic::StoreConstAction;
+use completion::Completion;
 
 pub struct ParseAction<'a, T: 'a> {
     pub cell: Rc<RefCell<&'a mut T>>,
@@ -24,18 +28,40 @@ impl<T: 'static + FromCommandLine> TypedAction<T> for Parse {
     fn bind<'x>(&self, cell: Rc<RefCell<&'x mut T>>) -> Action<'x> {
         return Single(Box::new(ParseAction { cell: cell }));
     }
+    fn completion(&self) -> Completion {
+        return T::completion();
+    }
+}
+
+impl<T> TypedAction<T> for ParseOrConst<T>
+    where T: 'static + FromCommandLine + Clone
+{
+    fn bind<'x>(&self, cell: Rc<RefCell<&'x mut T>>) -> Action<'x> {
+        let ParseOrConst(ref val) = *self;
+        return Optional(Box::new(ParseAction { cell: cell.clone() }),
+            Box::new(StoreConstAction { cell: cell, value: val.clone() }));
+    }
+    fn completion(&self) -> Completion {
+        return T::completion();
+    }
 }
 
 impl<T: 'static + FromCommandLine> TypedAction<Option<T>> for ParseOption {
     fn bind<'x>(&self, cell: Rc<RefCell<&'x mut Option<T>>>) -> Action<'x> {
         return Single(Box::new(ParseOptionAction { cell: cell }));
     }
+    fn completion(&self) -> Completion {
+        return T::completion();
+    }
 }
 
 impl<T: 'static + FromCommandLine + Clone> TypedAction<Vec<T>> for ParseList {
     fn bind<'x>(&self, cell: Rc<RefCell<&'x mut Vec<T>>>) -> Action<'x> {
         return Many(Box::new(ParseListAction { cell: cell }));
     }
+    fn completion(&self) -> Completion {
+        return T::completion();
+    }
 }
 
 impl<T> TypedAction<Vec<T>> for ParseCollect
@@ -44,17 +70,24 @@ impl<T> TypedAction<Vec<T>> for ParseCollect
     fn bind<'x>(&self, cell: Rc<RefCell<&'x mut Vec<T>>>) -> Action<'x> {
         return Push(Box::new(ParseListAction { cell: cell }))
     }
+    fn completion(&self) -> Completion {
+        return T::completion();
+    }
 }
 
 impl<'a, T: FromCommandLine> IArgAction for ParseAction<'a, T> {
     fn parse_arg(&self, arg: &str) -> ParseResult {
-        match FromCommandLine::from_argument(arg) {
+        return self.parse_os_arg(OsStr::new(arg));
+    }
+    fn parse_os_arg(&self, arg: &OsStr) -> ParseResult {
+        match FromCommandLine::from_os_argument(arg) {
             Ok(x) => {
                 **self.cell.borrow_mut() = x;
                 return Parsed;
             }
             Err(error) => {
-                return Error(format!("Bad value {:?}: {}", arg, error));
+                return BadValue(arg.to_string_lossy().into_owned(),
+                    Some(error));
             }
         }
     }
@@ -62,13 +95,17 @@ impl<'a, T: FromCommandLine> IArgAction for ParseAction<'a, T> {
 
 impl<'a, T: FromCommandLine> IArgAction for ParseOptionAction<'a, T> {
     fn parse_arg(&self, arg: &str) -> ParseResult {
-        match FromCommandLine::from_argument(arg) {
+        return self.parse_os_arg(OsStr::new(arg));
+    }
+    fn parse_os_arg(&self, arg: &OsStr) -> ParseResult {
+        match FromCommandLine::from_os_argument(arg) {
             Ok(x) => {
                 **self.cell.borrow_mut() = Some(x);
                 return Parsed;
             }
             Err(error) => {
-                return Error(format!("Bad value {:?}: {}", arg, error));
+                return BadValue(arg.to_string_lossy().into_owned(),
+                    Some(error));
             }
         }
     }
@@ -76,14 +113,19 @@ impl<'a, T: FromCommandLine> IArgAction for ParseOptionAction<'a, T> {
 
 impl<'a, T: FromCommandLine + Clone> IArgsAction for ParseListAction<'a, T> {
     fn parse_args(&self, args: &[&str]) -> ParseResult {
+        let args: Vec<_> = args.iter().map(OsStr::new).collect();
+        return self.parse_os_args(&args[..]);
+    }
+    fn parse_os_args(&self, args: &[&OsStr]) -> ParseResult {
         let mut result = vec!();
         for arg in args.iter() {
-            match FromCommandLine::from_argument(*arg) {
+            match FromCommandLine::from_os_argument(*arg) {
                 Ok(x) => {
                     result.push(x);
                 }
                 Err(error) => {
-                    return Error(format!("Bad value {:?}: {}", arg, error));
+                    return BadValue(arg.to_string_lossy().into_owned(),
+                        Some(error));
                 }
             }
         }
@@ -91,5 +133,3 @@ impl<'a, T: FromCommandLine + Clone> IArgsAction for ParseListAction<'a, T> {
         return Parsed;
     }
 }
-
-
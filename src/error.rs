@@ -0,0 +1,189 @@
+use std::error::Error;
+use std::fmt;
+
+use action::Arity;
+
+
+/// An error (or other reason to stop) encountered while parsing arguments
+///
+/// Returned by `ArgumentParser::try_parse`. The `Display` implementation
+/// produces the same message that `ArgumentParser::parse` writes to stderr.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The `--help` option was encountered, help is already printed
+    Help,
+    /// An action (e.g. `Print`) requested to exit with zero status
+    Exit,
+    /// Option is not known to the parser (as written on the command-line)
+    ///
+    /// The `suggestion` is the closest known option, if any
+    UnknownOption {
+        option: String,
+        suggestion: Option<String>,
+    },
+    /// Subcommand is not known to the parser
+    ///
+    /// The `suggestion` is the closest known command, if any
+    UnknownCommand {
+        command: String,
+        suggestion: Option<String>,
+    },
+    /// Option requires an argument but none was given (the list of all
+    /// names of the option)
+    MissingValue(Vec<String>),
+    /// List option or positional argument is given a number of values
+    /// not allowed by its arity (see `Ref::nargs`)
+    WrongNumberOfValues {
+        name: String,
+        arity: Arity,
+        got: usize,
+    },
+    /// Flag option is given a value using `--flag=value` syntax
+    UnexpectedValue {
+        option: String,
+        value: String,
+    },
+    /// The value can't be parsed by the action
+    ///
+    /// The `name` is the option, positional argument or environment
+    /// variable name. The `reason` contains an error of the
+    /// `FromCommandLine` implementation, and is `None` for `FromStr` based
+    /// actions (i.e. `Store`, `List`, ...). The `suggestion` is the
+    /// closest of the values listed by `Completion::Values`, if any
+    BadValue {
+        name: String,
+        value: String,
+        metavar: String,
+        reason: Option<String>,
+        suggestion: Option<String>,
+    },
+    /// Two options that can't be used together are specified
+    ///
+    /// Both are names as written on the command-line
+    ConflictingOptions {
+        option: String,
+        other: String,
+    },
+    /// The `option` requires another option (or argument) which is missing
+    MissingRequirement {
+        option: String,
+        requirement: String,
+    },
+    /// Extra positional argument
+    UnexpectedArgument(String),
+    /// Required positional argument is missing
+    MissingArgument(String),
+    /// Required option is missing (the list of all alternative names)
+    MissingOption(Vec<String>),
+    /// Required subcommand is missing (the list of all commands)
+    MissingCommand(Vec<String>),
+    /// Required environment variable is missing
+    MissingEnvVar(String),
+    /// Response file (`@path` argument) can't be read or parsed
+    ///
+    /// The `line` is `None` if the file is named on the command-line, and
+    /// is a line of the including file otherwise
+    ResponseFile {
+        path: String,
+        line: Option<usize>,
+        message: String,
+    },
+    /// An error returned by a custom action
+    Custom(String),
+}
+
+impl ParseError {
+    /// Returns exit code conventionally used for the error
+    ///
+    /// I.e. zero on `--help` and `2` on argument error
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            ParseError::Help | ParseError::Exit => 0,
+            _ => 2,
+        }
+    }
+}
+
+fn write_suggestion(f: &mut fmt::Formatter, suggestion: &Option<String>)
+    -> fmt::Result
+{
+    match *suggestion {
+        Some(ref suggestion) => write!(f, ", did you mean {}?", suggestion),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ParseError::*;
+        match *self {
+            Help => write!(f, "Help requested"),
+            Exit => write!(f, "Exit requested"),
+            UnknownOption { ref option, ref suggestion }
+                if !option.starts_with("--")
+            => {
+                try!(write!(f, "Unknown short option \"{}\"", &option[1..]));
+                write_suggestion(f, suggestion)
+            }
+            UnknownOption { ref option, ref suggestion } => {
+                try!(write!(f, "Unknown option {}", option));
+                write_suggestion(f, suggestion)
+            }
+            UnknownCommand { ref command, ref suggestion } => {
+                try!(write!(f, "Unknown command {}", command));
+                write_suggestion(f, suggestion)
+            }
+            MissingValue(ref names) => {
+                write!(f, "Option {:?} requires an argument", names)
+            }
+            WrongNumberOfValues { ref name, ref arity, got } => {
+                write!(f, "{} requires {}, got {}", name, arity, got)
+            }
+            UnexpectedValue { ref option, .. } => {
+                write!(f, "Option {} does not accept an argument", option)
+            }
+            BadValue { ref value, reason: Some(ref reason),
+                       ref suggestion, .. } => {
+                try!(write!(f, "Bad value {:?}: {}", value, reason));
+                write_suggestion(f, suggestion)
+            }
+            BadValue { ref value, reason: None, ref suggestion, .. } => {
+                try!(write!(f, "Bad value {}", value));
+                write_suggestion(f, suggestion)
+            }
+            ConflictingOptions { ref option, ref other } => {
+                write!(f, "Option {} cannot be used with {}", option, other)
+            }
+            MissingRequirement { ref option, ref requirement } => {
+                write!(f, "{} requires {}", option, requirement)
+            }
+            UnexpectedArgument(ref arg) => {
+                write!(f, "Unexpected argument {}", arg)
+            }
+            MissingArgument(ref name) => {
+                write!(f, "Argument {} is required", name)
+            }
+            MissingOption(ref names) if names.len() == 1 => {
+                write!(f, "Option {:?} is required", names)
+            }
+            MissingOption(ref names) => {
+                write!(f, "One of the options {:?} is required", names)
+            }
+            MissingCommand(ref names) => {
+                write!(f, "One of the commands {:?} is required", names)
+            }
+            MissingEnvVar(ref name) => {
+                write!(f, "Environment var {} is required", name)
+            }
+            ResponseFile { ref path, line: Some(line), ref message } => {
+                write!(f, "Response file {}:{}: {}", path, line, message)
+            }
+            ResponseFile { ref path, line: None, ref message } => {
+                write!(f, "Response file {}: {}", path, message)
+            }
+            Custom(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ParseError {}
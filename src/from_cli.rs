@@ -1,14 +1,31 @@
 use std::str::FromStr;
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 
 use super::FromCommandLine;
+use completion::Completion;
 
 
 impl FromCommandLine for PathBuf {
     fn from_argument(s: &str) -> Result<Self, String> {
         Ok(From::from(s))
     }
+    fn from_os_argument(s: &OsStr) -> Result<Self, String> {
+        Ok(From::from(s))
+    }
+    fn completion() -> Completion {
+        Completion::Files
+    }
+}
+
+impl FromCommandLine for OsString {
+    fn from_argument(s: &str) -> Result<Self, String> {
+        Ok(From::from(s))
+    }
+    fn from_os_argument(s: &OsStr) -> Result<Self, String> {
+        Ok(s.to_os_string())
+    }
 }
 
 impl FromCommandLine for f32 {
@@ -3,11 +3,12 @@ use std::str::FromStr;
 use std::rc::Rc;
 
 use super::{StoreConst, Store, StoreOption, List, Collect, PushConst};
+use super::StoreOrConst;
 use super::action::Action;
 use super::action::{TypedAction, IFlagAction, IArgAction, IArgsAction};
 use super::action::ParseResult;
-use super::action::ParseResult::{Parsed, Error};
-use super::action::Action::{Flag, Single, Push, Many};
+use super::action::ParseResult::{Parsed, BadValue};
+use super::action::Action::{Flag, Single, Optional, Push, Many};
 
 pub struct StoreConstAction<'a, T: 'a> {
     pub value: T,
@@ -51,6 +52,14 @@ impl<T: 'static + FromStr> TypedAction<T> for Store {
     }
 }
 
+impl<T: 'static + FromStr + Clone> TypedAction<T> for StoreOrConst<T> {
+    fn bind<'x>(&self, cell: Rc<RefCell<&'x mut T>>) -> Action<'x> {
+        let StoreOrConst(ref val) = *self;
+        return Optional(Box::new(StoreAction { cell: cell.clone() }),
+            Box::new(StoreConstAction { cell: cell, value: val.clone() }));
+    }
+}
+
 impl<T: 'static + FromStr> TypedAction<Option<T>> for StoreOption {
     fn bind<'x>(&self, cell: Rc<RefCell<&'x mut Option<T>>>) -> Action<'x> {
         return Single(Box::new(StoreOptionAction { cell: cell }));
@@ -93,7 +102,7 @@ impl<'a, T: FromStr> IArgAction for StoreAction<'a, T> {
                 return Parsed;
             }
             Err(_) => {
-                return Error(format!("Bad value {}", arg));
+                return BadValue(arg.to_string(), None);
             }
         }
     }
@@ -107,7 +116,7 @@ impl<'a, T: FromStr> IArgAction for StoreOptionAction<'a, T> {
                 return Parsed;
             }
             Err(_) => {
-                return Error(format!("Bad value {}", arg));
+                return BadValue(arg.to_string(), None);
             }
         }
     }
@@ -122,7 +131,7 @@ impl<'a, T: FromStr + Clone> IArgsAction for ListAction<'a, T> {
                     result.push(x);
                 }
                 Err(_) => {
-                    return Error(format!("Bad value {}", arg));
+                    return BadValue(arg.to_string(), None);
                 }
             }
         }
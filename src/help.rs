@@ -91,3 +91,67 @@ pub fn wrap_text(buf: &mut Write, data: &str, width: usize, indent: usize)
     }
     return Ok(());
 }
+
+/// Wraps text like `wrap_text` keeping blank lines between paragraphs
+///
+/// Every paragraph (including the first one) starts at `indent`
+pub fn wrap_paragraphs(buf: &mut Write, data: &str, width: usize,
+    indent: usize)
+    -> IoResult<()>
+{
+    let mut first = true;
+    for paragraph in paragraphs(data) {
+        if !first {
+            try!(buf.write_all(b"\n\n"));
+        }
+        first = false;
+        for _ in 0..indent {
+            try!(buf.write_all(b" "));
+        }
+        try!(wrap_text(buf, &paragraph, width, indent));
+    }
+    return Ok(());
+}
+
+/// Splits text into paragraphs separated by blank lines
+pub fn paragraphs(data: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    for line in data.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                result.push(current);
+                current = String::new();
+            }
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    if !current.is_empty() {
+        result.push(current);
+    }
+    return result;
+}
+
+/// Removes common indentation and leading and trailing blank lines
+///
+/// Used for preformatted text that is written as is
+pub fn dedent(data: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = data.lines()
+        .map(|line| line.trim_end())
+        .skip_while(|line| line.is_empty())
+        .collect();
+    while lines.last().map(|line| line.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+    let indent = lines.iter()
+        .filter(|line| !line.is_empty())
+        // only ASCII indentation, so slicing stays at a char boundary
+        .map(|line| line.len() - line.trim_start_matches(&[' ', '\t'][..])
+                                     .len())
+        .min().unwrap_or(0);
+    return lines.iter()
+        .map(|line| if line.is_empty() { *line } else { &line[indent..] })
+        .collect();
+}
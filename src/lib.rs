@@ -1,12 +1,37 @@
 #![crate_name = "argparse"]
 #![crate_type = "lib"]
 
+#[cfg(unix)] extern crate libc;
+
+use std::ffi::OsStr;
+
 pub use self::parser::{ArgumentParser, Ref};
+pub use self::error::ParseError;
+pub use self::action::Arity;
+pub use self::completion::{Completion, Shell};
+pub use self::owned::{OwnedParser, OwnedRef, Handle, Values};
+pub use self::response::ResponseFiles;
+pub use self::config::{Config, ConfigError};
+pub use self::origin::{Source, Origin, VarOrigin, Provenance};
+pub use self::style::{ColorChoice, Theme};
+pub use self::map::{CollectMap, ParseMap, DuplicateKeys};
 
 pub mod action;
 pub mod parser;
+mod error;
+mod completion;
+mod man;
+mod owned;
+mod suggest;
+mod response;
+mod config;
+mod origin;
+mod style;
+mod terminal;
 mod generic;
 mod custom;
+mod tuple;
+mod map;
 mod help;
 mod print;
 
@@ -16,6 +41,23 @@ mod from_cli;
 
 pub trait FromCommandLine: Sized {
     fn from_argument(s: &str) -> Result<Self, String>;
+    /// Parse the value which may be not valid UTF-8
+    ///
+    /// By default fails on invalid UTF-8 and calls `from_argument`
+    /// otherwise. `PathBuf` and `OsString` receive the raw value.
+    fn from_os_argument(s: &OsStr) -> Result<Self, String> {
+        match s.to_str() {
+            Some(s) => return Self::from_argument(s),
+            None => return Err("invalid utf-8".to_string()),
+        }
+    }
+    /// What shell completion should offer for the value
+    ///
+    /// Used by the `Parse` family of actions, e.g. `PathBuf` completes
+    /// file names
+    fn completion() -> Completion {
+        return Completion::Default;
+    }
 }
 
 // TODO(tailhook) make consts
@@ -29,6 +71,15 @@ pub struct PushConst<T>(pub T);
 pub struct Store;
 pub struct Parse;
 
+/// Store the value if given, or the constant if the option has no value
+///
+/// The value is taken only when attached (`--opt=value`, `-ovalue`) or
+/// when the next argument doesn't look like an option (like
+/// `nargs='?'` with `const` in Python's argparse)
+pub struct StoreOrConst<T>(pub T);
+/// Same as `StoreOrConst` but parses the value with `FromCommandLine`
+pub struct ParseOrConst<T>(pub T);
+
 pub struct StoreOption;
 pub struct ParseOption;
 
@@ -47,6 +98,15 @@ pub struct IncrBy<T>(pub T);
 
 pub struct DecrBy<T>(pub T);
 
+/// A structure which can declare its fields as options of a parser
+///
+/// Usually implemented by `#[derive(Declare)]` from the `argparse_derive`
+/// crate, but may be written by hand to group related options
+pub trait Declare {
+    fn declare<'parser>(&'parser mut self,
+        ap: &mut ArgumentParser<'parser>);
+}
+
 
 #[cfg(test)] mod test_parser;
 #[cfg(test)] mod test_bool;
@@ -62,3 +122,26 @@ pub struct DecrBy<T>(pub T);
 #[cfg(test)] mod test_env;
 #[cfg(test)] mod test_const;
 #[cfg(test)] mod test_path;
+#[cfg(test)] mod test_subcommand;
+#[cfg(test)] mod test_error;
+#[cfg(test)] mod test_completion;
+#[cfg(test)] mod test_man;
+#[cfg(test)] mod test_owned;
+#[cfg(test)] mod test_exclusive;
+#[cfg(test)] mod test_constraints;
+#[cfg(all(test, unix))] mod test_os_str;
+#[cfg(test)] mod test_response;
+#[cfg(test)] mod test_config;
+#[cfg(test)] mod test_origin;
+#[cfg(test)] mod test_style;
+#[cfg(test)] mod test_width;
+#[cfg(test)] mod test_groups;
+#[cfg(test)] mod test_sections;
+#[cfg(test)] mod test_annotations;
+#[cfg(test)] mod test_hidden;
+#[cfg(test)] mod test_negatable;
+#[cfg(test)] mod test_or_const;
+#[cfg(test)] mod test_nargs;
+#[cfg(test)] mod test_tuple;
+#[cfg(test)] mod test_map;
+#[cfg(test)] mod test_delimiter;
@@ -0,0 +1,285 @@
+use std::io::Write;
+use std::io::Result as IoResult;
+
+use action::Action::{Flag, Single, Optional, Push, Many};
+use parser::{ArgumentParser, GenericOption, repeat_metavar};
+use help::{paragraphs, dedent};
+
+
+fn escape(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ")
+        .replace("\\", "\\e");
+    if text.starts_with('.') || text.starts_with('\'') {
+        return format!("\\&{}", text);
+    }
+    return text;
+}
+
+/// Escapes a line of preformatted text keeping the whitespace
+fn escape_line(line: &str) -> String {
+    let line = line.replace("\\", "\\e");
+    if line.starts_with('.') || line.starts_with('\'') {
+        return format!("\\&{}", line);
+    }
+    return line;
+}
+
+fn escape_name(name: &str) -> String {
+    return escape(name).replace("-", "\\-");
+}
+
+/// First sentence of the description, used in the NAME section
+fn summary(description: &str) -> String {
+    let text = escape(description);
+    let mut end = text.len();
+    for (idx, _) in text.match_indices('.') {
+        if text[idx+1..].is_empty() || text[idx+1..].starts_with(' ') {
+            end = idx;
+            break;
+        }
+    }
+    return text[..end].to_string();
+}
+
+pub struct ManFormatter<'a, 'b: 'a> {
+    name: &'a str,
+    parser: &'a ArgumentParser<'b>,
+    buf: &'a mut (Write + 'a),
+}
+
+impl<'a, 'b> ManFormatter<'a, 'b> {
+    pub fn print_man_page(parser: &ArgumentParser, name: &str,
+        section: &str, writer: &mut Write)
+        -> IoResult<()>
+    {
+        return ManFormatter { parser: parser, name: name, buf: writer }
+            .write_man_page(section);
+    }
+
+    fn write_synopsis(&mut self) -> IoResult<()> {
+        try!(writeln!(self.buf, ".SH SYNOPSIS"));
+        try!(writeln!(self.buf, ".B {}", escape_name(self.name)));
+        if self.parser.has_options() {
+            try!(writeln!(self.buf, "[\\fIOPTIONS\\fR]"));
+        }
+        for arg in self.parser.arguments.iter() {
+            let var = &self.parser.vars[arg.varid];
+            if var.required {
+                try!(writeln!(self.buf, "\\fI{}\\fR",
+                    escape_name(&arg.name.to_ascii_uppercase())));
+            } else {
+                try!(writeln!(self.buf, "[\\fI{}\\fR]",
+                    escape_name(&arg.name.to_ascii_uppercase())));
+            }
+        }
+        match self.parser.catchall_argument {
+            Some(ref arg) if self.parser.vars[arg.varid].arity.is_some() => {
+                let var = &self.parser.vars[arg.varid];
+                let label = repeat_metavar(
+                    &format!("\\fI{}\\fR",
+                        escape_name(&arg.name.to_ascii_uppercase())),
+                    var.arity.unwrap());
+                if var.required {
+                    try!(writeln!(self.buf, "{}", label));
+                } else {
+                    try!(writeln!(self.buf, "[{}]", label));
+                }
+            }
+            Some(ref arg) => {
+                let var = &self.parser.vars[arg.varid];
+                if var.required {
+                    try!(writeln!(self.buf, "\\fI{}\\fR [...]",
+                        escape_name(&arg.name.to_ascii_uppercase())));
+                } else {
+                    try!(writeln!(self.buf, "[\\fI{}\\fR ...]",
+                        escape_name(&arg.name.to_ascii_uppercase())));
+                }
+            }
+            None => {}
+        }
+        match self.parser.subcommands.first() {
+            Some(sub) => {
+                let var = &self.parser.vars[sub.varid];
+                if var.required {
+                    try!(writeln!(self.buf, "\\fI{}\\fR [...]",
+                        escape_name(&var.metavar.to_ascii_uppercase())));
+                } else {
+                    try!(writeln!(self.buf, "[\\fI{}\\fR ...]",
+                        escape_name(&var.metavar.to_ascii_uppercase())));
+                }
+            }
+            None => {}
+        }
+        return Ok(());
+    }
+
+    fn write_option(&mut self, opt: &GenericOption) -> IoResult<()> {
+        let names = self.parser.visible_names(opt);
+        if names.is_empty() {
+            return Ok(());
+        }
+        try!(writeln!(self.buf, ".TP"));
+        let names: Vec<_> = names.iter()
+            .map(|name| format!("\\fB{}\\fR",
+                escape_name(&self.parser.display_name(opt, name))))
+            .collect();
+        try!(write!(self.buf, "{}", names.join(", ")));
+        let mut required = false;
+        match opt.action {
+            Flag(_) => {}
+            Single(_) | Push(_) | Many(_) => {
+                let var = &self.parser.vars[opt.varid.unwrap()];
+                try!(write!(self.buf, " \\fI{}\\fR",
+                    escape_name(&self.parser.value_label(opt))));
+                required = var.required;
+            }
+            Optional(..) => {
+                let var = &self.parser.vars[opt.varid.unwrap()];
+                try!(write!(self.buf, " [\\fI{}\\fR]",
+                    escape_name(&var.metavar)));
+                required = var.required;
+            }
+        }
+        if required {
+            try!(write!(self.buf, " (required)"));
+        }
+        try!(writeln!(self.buf, ""));
+        try!(writeln!(self.buf, "{}", escape(opt.help)));
+        return Ok(());
+    }
+
+    fn write_man_page(&mut self, section: &str) -> IoResult<()> {
+        try!(writeln!(self.buf, ".TH {} {}",
+            escape_name(&self.name.to_ascii_uppercase()), escape(section)));
+        try!(writeln!(self.buf, ".SH NAME"));
+        if self.parser.description.is_empty() {
+            try!(writeln!(self.buf, "{}", escape_name(self.name)));
+        } else {
+            try!(writeln!(self.buf, "{} \\- {}", escape_name(self.name),
+                summary(self.parser.description)));
+        }
+        try!(self.write_synopsis());
+        if !self.parser.description.is_empty() {
+            try!(writeln!(self.buf, ".SH DESCRIPTION"));
+            try!(writeln!(self.buf, "{}", escape(self.parser.description)));
+        }
+        try!(writeln!(self.buf, ".SH OPTIONS"));
+        for arg in self.parser.arguments.iter()
+            .chain(self.parser.catchall_argument.iter())
+        {
+            let var = &self.parser.vars[arg.varid];
+            try!(writeln!(self.buf, ".TP"));
+            try!(write!(self.buf, "\\fI{}\\fR", escape_name(arg.name)));
+            if var.required {
+                try!(write!(self.buf, " (required)"));
+            }
+            try!(writeln!(self.buf, ""));
+            try!(writeln!(self.buf, "{}", escape(arg.help)));
+        }
+        for opt in self.parser.options.iter()
+            .filter(|opt| opt.group.is_none())
+        {
+            try!(self.write_option(opt));
+        }
+        for (idx, group) in self.parser.help_groups.iter().enumerate() {
+            if !self.parser.options.iter().any(|opt| {
+                opt.group == Some(idx) && self.parser.is_visible(opt)
+            }) {
+                continue;
+            }
+            try!(writeln!(self.buf, ".SS {}", escape(group.title)));
+            if !group.description.is_empty() {
+                try!(writeln!(self.buf, "{}", escape(group.description)));
+            }
+            for opt in self.parser.options.iter()
+                .filter(|opt| opt.group == Some(idx))
+            {
+                try!(self.write_option(opt));
+            }
+        }
+        if !self.parser.subcommands.is_empty() {
+            try!(writeln!(self.buf, ".SH COMMANDS"));
+            for sub in self.parser.subcommands.iter() {
+                try!(writeln!(self.buf, ".TP"));
+                try!(writeln!(self.buf, "\\fB{}\\fR", escape_name(sub.name)));
+                try!(writeln!(self.buf, "{}", escape(sub.help)));
+            }
+        }
+        for section in self.parser.sections.iter() {
+            try!(writeln!(self.buf, ".SH {}",
+                escape(&section.title.to_uppercase())));
+            if section.verbatim {
+                try!(writeln!(self.buf, ".nf"));
+                for line in dedent(section.text) {
+                    try!(writeln!(self.buf, "{}", escape_line(line)));
+                }
+                try!(writeln!(self.buf, ".fi"));
+            } else {
+                for (idx, para) in paragraphs(section.text).iter()
+                    .enumerate()
+                {
+                    if idx > 0 {
+                        try!(writeln!(self.buf, ".PP"));
+                    }
+                    try!(writeln!(self.buf, "{}", escape(para)));
+                }
+            }
+        }
+        if !self.parser.examples.is_empty() {
+            try!(writeln!(self.buf, ".SH EXAMPLES"));
+            for example in self.parser.examples.iter() {
+                try!(writeln!(self.buf, ".PP"));
+                if !example.description.is_empty() {
+                    try!(writeln!(self.buf, "{}",
+                        escape(example.description)));
+                }
+                try!(writeln!(self.buf, ".RS\n.nf"));
+                try!(writeln!(self.buf, "{}", escape_line(example.command)));
+                try!(writeln!(self.buf, ".fi\n.RE"));
+            }
+        }
+        if !self.parser.env_vars.is_empty() {
+            try!(writeln!(self.buf, ".SH ENVIRONMENT"));
+            for evar in self.parser.env_vars.iter() {
+                try!(writeln!(self.buf, ".TP"));
+                try!(write!(self.buf, "\\fB{}\\fR", escape_name(evar.name)));
+                if self.parser.vars[evar.varid].required {
+                    try!(write!(self.buf, " (required)"));
+                }
+                try!(writeln!(self.buf, ""));
+                let option = self.parser.options.iter()
+                    .find(|opt| opt.varid == Some(evar.varid));
+                let argument = self.parser.arguments.iter()
+                    .chain(self.parser.catchall_argument.iter())
+                    .find(|arg| arg.varid == evar.varid);
+                match (option, argument) {
+                    (Some(opt), _) => {
+                        try!(writeln!(self.buf, "{}", escape(opt.help)));
+                        try!(writeln!(self.buf,
+                            "Overridden by \\fB{}\\fR.",
+                            escape_name(opt.names[0])));
+                    }
+                    (None, Some(arg)) => {
+                        try!(writeln!(self.buf, "{}", escape(arg.help)));
+                        try!(writeln!(self.buf,
+                            "Overridden by \\fI{}\\fR argument.",
+                            escape_name(arg.name)));
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        if !self.parser.epilog.is_empty() {
+            try!(writeln!(self.buf, ".SH NOTES"));
+            for (idx, para) in paragraphs(self.parser.epilog).iter()
+                .enumerate()
+            {
+                if idx > 0 {
+                    try!(writeln!(self.buf, ".PP"));
+                }
+                try!(writeln!(self.buf, "{}", escape(para)));
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,226 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, BTreeMap};
+use std::ffi::OsStr;
+use std::hash::Hash;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use super::FromCommandLine;
+use super::action::{Action, TypedAction, IArgsAction};
+use super::action::ParseResult;
+use super::action::ParseResult::{Parsed, BadValue};
+use super::action::Action::Push;
+
+
+/// What to do if a key of a map is given more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// The last value is used (the default)
+    LastWins,
+    /// Fail with a bad value error
+    Error,
+}
+
+/// Collect `key=value` entries into a `HashMap` or a `BTreeMap`
+///
+/// Each occurrence of the option adds an entry. Keys and values are
+/// parsed with `FromStr`.
+#[derive(Debug, Clone, Copy)]
+pub struct CollectMap {
+    separator: char,
+    duplicates: DuplicateKeys,
+}
+
+/// Same as `CollectMap` but parses keys and values with `FromCommandLine`
+#[derive(Debug, Clone, Copy)]
+pub struct ParseMap {
+    separator: char,
+    duplicates: DuplicateKeys,
+}
+
+impl CollectMap {
+    /// Entries separated by `=`, the last of duplicate keys wins
+    pub fn new() -> CollectMap {
+        return CollectMap {
+            separator: '=',
+            duplicates: DuplicateKeys::LastWins,
+        };
+    }
+    /// Set the separator of the key and the value (`=` by default)
+    pub fn separator(self, separator: char) -> CollectMap {
+        return CollectMap { separator: separator, ..self };
+    }
+    /// Set what to do if a key is given more than once
+    pub fn duplicates(self, duplicates: DuplicateKeys) -> CollectMap {
+        return CollectMap { duplicates: duplicates, ..self };
+    }
+}
+
+impl ParseMap {
+    /// Entries separated by `=`, the last of duplicate keys wins
+    pub fn new() -> ParseMap {
+        return ParseMap {
+            separator: '=',
+            duplicates: DuplicateKeys::LastWins,
+        };
+    }
+    /// Set the separator of the key and the value (`=` by default)
+    pub fn separator(self, separator: char) -> ParseMap {
+        return ParseMap { separator: separator, ..self };
+    }
+    /// Set what to do if a key is given more than once
+    pub fn duplicates(self, duplicates: DuplicateKeys) -> ParseMap {
+        return ParseMap { duplicates: duplicates, ..self };
+    }
+}
+
+/// Parses a key or a value, the error is a reason (if known)
+type Parser<T> = fn(&OsStr) -> Result<T, Option<String>>;
+
+pub struct MapAction<'a, K, V, M: 'a> {
+    cell: Rc<RefCell<&'a mut M>>,
+    separator: char,
+    duplicates: DuplicateKeys,
+    key: Parser<K>,
+    value: Parser<V>,
+}
+
+fn from_str<T: FromStr>(arg: &OsStr) -> Result<T, Option<String>> {
+    match arg.to_str() {
+        Some(arg) => return arg.parse().map_err(|_| None),
+        None => return Err(Some("invalid utf-8".to_string())),
+    }
+}
+
+fn from_cli<T: FromCommandLine>(arg: &OsStr) -> Result<T, Option<String>> {
+    return FromCommandLine::from_os_argument(arg).map_err(Some);
+}
+
+/// Split the `arg` at the first `separator`
+fn split(arg: &OsStr, separator: char) -> Option<(&OsStr, &OsStr)> {
+    let mut buf = [0; 4];
+    let sep = separator.encode_utf8(&mut buf).as_bytes();
+    let bytes = arg.as_encoded_bytes();
+    let idx = match bytes.windows(sep.len()).position(|x| x == sep) {
+        Some(idx) => idx,
+        None => return None,
+    };
+    // Safe because both parts are split at a valid UTF-8 character
+    unsafe {
+        return Some((OsStr::from_encoded_bytes_unchecked(&bytes[..idx]),
+            OsStr::from_encoded_bytes_unchecked(&bytes[idx+sep.len()..])));
+    }
+}
+
+fn bad_entry(entry: &OsStr, reason: String) -> ParseResult {
+    return BadValue(entry.to_string_lossy().into_owned(), Some(reason));
+}
+
+impl<'a, K, V, M> MapAction<'a, K, V, M> {
+    /// Parse all entries, `insert` returns whether the key was already set
+    fn parse_entries<F>(&self, args: &[&OsStr], mut insert: F)
+        -> ParseResult
+        where F: FnMut(K, V) -> bool
+    {
+        for entry in args.iter() {
+            let (key, value) = match split(entry, self.separator) {
+                Some(pair) => pair,
+                None => {
+                    return bad_entry(entry,
+                        format!("expected KEY{}VALUE", self.separator));
+                }
+            };
+            let key_text = key.to_string_lossy();
+            let parsed_key = match (self.key)(key) {
+                Ok(key) => key,
+                Err(None) => {
+                    return bad_entry(entry, format!("bad key {:?}", key_text));
+                }
+                Err(Some(reason)) => {
+                    return bad_entry(entry,
+                        format!("bad key {:?}: {}", key_text, reason));
+                }
+            };
+            let parsed_value = match (self.value)(value) {
+                Ok(value) => value,
+                Err(None) => {
+                    return bad_entry(entry, format!("bad value {:?}",
+                        value.to_string_lossy()));
+                }
+                Err(Some(reason)) => {
+                    return bad_entry(entry, format!("bad value {:?}: {}",
+                        value.to_string_lossy(), reason));
+                }
+            };
+            if insert(parsed_key, parsed_value)
+                && self.duplicates == DuplicateKeys::Error
+            {
+                return bad_entry(entry,
+                    format!("duplicate key {:?}", key_text));
+            }
+        }
+        return Parsed;
+    }
+}
+
+macro_rules! map_impls {
+    ($map:ident, $($bound:tt)+) => {
+        impl<K, V> TypedAction<$map<K, V>> for CollectMap
+            where K: 'static + FromStr + $($bound)+,
+                  V: 'static + FromStr,
+        {
+            fn bind<'x>(&self, cell: Rc<RefCell<&'x mut $map<K, V>>>)
+                -> Action<'x>
+            {
+                return Push(Box::new(MapAction {
+                    cell: cell,
+                    separator: self.separator,
+                    duplicates: self.duplicates,
+                    key: from_str::<K>,
+                    value: from_str::<V>,
+                }));
+            }
+        }
+
+        impl<K, V> TypedAction<$map<K, V>> for ParseMap
+            where K: 'static + FromCommandLine + $($bound)+,
+                  V: 'static + FromCommandLine,
+        {
+            fn bind<'x>(&self, cell: Rc<RefCell<&'x mut $map<K, V>>>)
+                -> Action<'x>
+            {
+                return Push(Box::new(MapAction {
+                    cell: cell,
+                    separator: self.separator,
+                    duplicates: self.duplicates,
+                    key: from_cli::<K>,
+                    value: from_cli::<V>,
+                }));
+            }
+        }
+
+        impl<'a, K, V> IArgsAction for MapAction<'a, K, V, $map<K, V>>
+            where K: $($bound)+
+        {
+            fn parse_args(&self, args: &[&str]) -> ParseResult {
+                let args: Vec<_> = args.iter().map(OsStr::new).collect();
+                return self.parse_os_args(&args[..]);
+            }
+            fn parse_os_args(&self, args: &[&OsStr]) -> ParseResult {
+                let mut result = $map::new();
+                let res = self.parse_entries(args,
+                    |key, value| result.insert(key, value).is_some());
+                match res {
+                    Parsed => {
+                        **self.cell.borrow_mut() = result;
+                        return Parsed;
+                    }
+                    res => return res,
+                }
+            }
+        }
+    }
+}
+
+map_impls!(HashMap, Eq + Hash);
+map_impls!(BTreeMap, Ord);
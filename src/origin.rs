@@ -0,0 +1,64 @@
+use std::slice::Iter;
+
+
+/// Kind of the source a value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The variable kept its initial value
+    Default,
+    /// An option or positional argument on the command-line
+    CommandLine,
+    /// Environment variable (see `Ref::envvar`)
+    EnvVar,
+    /// Configuration (see `Ref::config_key`)
+    Config,
+}
+
+/// Where a value of the variable came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub source: Source,
+    /// The option name as written on the command-line, positional argument
+    /// name, environment variable name or configuration key (empty for
+    /// `Source::Default`)
+    pub name: String,
+    /// Raw values (empty for flags and defaults)
+    pub values: Vec<String>,
+}
+
+/// Provenance of a single variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarOrigin {
+    /// All option names, positional argument names, environment variables
+    /// and configuration keys of the variable
+    pub names: Vec<String>,
+    /// The source of the final value
+    pub origin: Origin,
+    /// Earlier sources which were overridden, in the order of application
+    /// (e.g. an environment variable overridden by an option)
+    pub overridden: Vec<Origin>,
+}
+
+/// Provenance report of all variables, returned by
+/// `ArgumentParser::try_parse_provenance`
+///
+/// Variables of the subcommands are included after the ones of the parent
+/// parser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub(crate) vars: Vec<VarOrigin>,
+}
+
+impl Provenance {
+    /// Find a variable by any of its names (e.g. `--port`, `PORT` or
+    /// `server.port`)
+    pub fn get(&self, name: &str) -> Option<&VarOrigin> {
+        return self.vars.iter()
+            .find(|var| var.names.iter().any(|n| n == name));
+    }
+
+    /// Iterate over all variables in the order of declaration
+    pub fn iter(&self) -> Iter<VarOrigin> {
+        return self.vars.iter();
+    }
+}
@@ -0,0 +1,760 @@
+use std::any::Any;
+use std::env;
+use std::ffi::OsString;
+use std::io::Write;
+use std::io::Result as IoResult;
+use std::marker::PhantomData;
+use std::process::exit;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use action::{Action, TypedAction, IFlagAction, ParseResult, Arity};
+use completion::{Completion, Shell};
+use error::ParseError;
+use parser::{ArgumentParser, Ref};
+use response::ResponseFiles;
+use config::Config;
+use origin::Provenance;
+use style::{ColorChoice, Theme};
+
+
+/// A reference to the value of a variable declared in `OwnedParser`
+///
+/// Used to fetch the parsed value from `Values`.
+pub struct Handle<T> {
+    index: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Handle<T> {
+        return Handle { index: self.index, marker: PhantomData };
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+/// Values returned by `OwnedParser::parse`
+pub struct Values {
+    values: Vec<Option<Box<Any>>>,
+}
+
+impl Values {
+    /// Get the value of a variable
+    ///
+    /// Panics if value is already taken by `take` or if the handle belongs
+    /// to another parser
+    pub fn get<T: 'static>(&self, handle: &Handle<T>) -> &T {
+        return self.values[handle.index].as_ref()
+            .expect("value is already taken")
+            .downcast_ref::<T>()
+            .expect("handle belongs to another parser");
+    }
+
+    /// Move the value of a variable out of the result
+    ///
+    /// Panics if value is already taken or if the handle belongs to
+    /// another parser
+    pub fn take<T: 'static>(&mut self, handle: &Handle<T>) -> T {
+        let value = self.values[handle.index].take()
+            .expect("value is already taken");
+        return *value.downcast::<T>()
+            .ok().expect("handle belongs to another parser");
+    }
+}
+
+struct SharedAction<'a, T: 'a>(&'a TypedAction<T>);
+
+impl<'a, T> TypedAction<T> for SharedAction<'a, T> {
+    fn bind<'x>(&self, cell: Rc<RefCell<&'x mut T>>) -> Action<'x> {
+        return self.0.bind(cell);
+    }
+    fn completion(&self) -> Completion {
+        return self.0.completion();
+    }
+    fn arity(&self) -> Option<Arity> {
+        return self.0.arity();
+    }
+}
+
+struct SharedFlag<'a>(&'a IFlagAction);
+
+impl<'a> IFlagAction for SharedFlag<'a> {
+    fn parse_flag(&self) -> ParseResult {
+        return self.0.parse_flag();
+    }
+}
+
+enum Setting<T> {
+    Option(Vec<String>, Box<TypedAction<T>>, String),
+    Argument(String, Box<TypedAction<T>>, String),
+    EnvVar(String, for<'p, 'r> fn(&mut Ref<'p, 'r, T>, &'p str)),
+    ConfigKey(String, Box<TypedAction<T>>),
+    NegatableOption(Vec<String>, String,
+        for<'p, 'r> fn(&mut Ref<'p, 'r, T>, &[&'p str], &'p str)),
+    Metavar(String),
+    Completion(Completion),
+    Nargs(Arity),
+    Delimiter(char),
+    Required,
+    Requires(String),
+    ConflictsWith(String),
+    RequiredIf(String),
+    Default(String),
+}
+
+fn add_envvar<'p, 'r, T: 'static + FromStr>(var: &mut Ref<'p, 'r, T>,
+    name: &'p str)
+{
+    var.envvar(name);
+}
+
+fn add_envvar_list<'p, 'r, T>(var: &mut Ref<'p, 'r, Vec<T>>, name: &'p str)
+    where T: 'static + FromStr + Clone
+{
+    var.envvar_list(name);
+}
+
+fn add_negatable_option<'p, 'r>(var: &mut Ref<'p, 'r, bool>,
+    names: &[&'p str], help: &'p str)
+{
+    var.add_negatable_option(names, help);
+}
+
+trait Variable {
+    fn default_value(&self) -> Box<Any>;
+    fn declare<'p>(&'p self, parser: &mut ArgumentParser<'p>,
+        value: &'p mut Any);
+    fn as_any_mut(&mut self) -> &mut Any;
+}
+
+struct TypedVariable<T> {
+    default: T,
+    settings: Vec<Setting<T>>,
+}
+
+impl<T: 'static + Clone> Variable for TypedVariable<T> {
+    fn default_value(&self) -> Box<Any> {
+        return Box::new(self.default.clone());
+    }
+    fn declare<'p>(&'p self, parser: &mut ArgumentParser<'p>,
+        value: &'p mut Any)
+    {
+        let value = value.downcast_mut::<T>().unwrap();
+        let mut var = parser.refer(value);
+        for setting in self.settings.iter() {
+            match *setting {
+                Setting::Option(ref names, ref action, ref help) => {
+                    let names: Vec<_> = names.iter().map(|x| &x[..])
+                        .collect();
+                    var.add_option(&names, SharedAction(&**action), help);
+                }
+                Setting::Argument(ref name, ref action, ref help) => {
+                    var.add_argument(name, SharedAction(&**action), help);
+                }
+                Setting::EnvVar(ref name, add) => {
+                    add(&mut var, name);
+                }
+                Setting::ConfigKey(ref key, ref action) => {
+                    var.config_key(key, SharedAction(&**action));
+                }
+                Setting::NegatableOption(ref names, ref help, add) => {
+                    let names: Vec<_> = names.iter().map(|x| &x[..])
+                        .collect();
+                    add(&mut var, &names, help);
+                }
+                Setting::Metavar(ref name) => {
+                    var.metavar(name);
+                }
+                Setting::Completion(ref completion) => {
+                    var.completion(completion.clone());
+                }
+                Setting::Nargs(arity) => {
+                    var.nargs(arity);
+                }
+                Setting::Delimiter(delimiter) => {
+                    var.delimiter(delimiter);
+                }
+                Setting::Required => {
+                    var.required();
+                }
+                Setting::Requires(ref name) => {
+                    var.requires(name);
+                }
+                Setting::ConflictsWith(ref name) => {
+                    var.conflicts_with(name);
+                }
+                Setting::RequiredIf(ref name) => {
+                    var.required_if(name);
+                }
+                Setting::Default(ref value) => {
+                    var.default_text(value.clone());
+                }
+            }
+        }
+    }
+    fn as_any_mut(&mut self) -> &mut Any {
+        return self;
+    }
+}
+
+enum Item {
+    Variable(Box<Variable>),
+    Flag(Vec<String>, Box<IFlagAction>, String),
+    ColorOption,
+    Group(String, String),
+}
+
+/// Argument parser which owns parsed values
+///
+/// Unlike `ArgumentParser` it doesn't borrow variables, so it can be stored
+/// in a structure or returned from a function. Each variable is declared
+/// with a default value, and returns `Handle` which is used to fetch the
+/// value from `Values` returned by `parse`.
+pub struct OwnedParser {
+    description: String,
+    epilog: String,
+    examples: Vec<(String, String)>,
+    sections: Vec<(String, String, bool)>,
+    items: Vec<Item>,
+    variables: Vec<usize>,
+    exclusive_groups: Vec<(Vec<String>, bool)>,
+    hidden_options: Vec<String>,
+    deprecated_options: Vec<(String, String)>,
+    stop_on_first_argument: bool,
+    silence_double_dash: bool,
+    response_files: ResponseFiles,
+    config: Option<Config>,
+    color: ColorChoice,
+    theme: Theme,
+    width: Option<usize>,
+    option_width: Option<usize>,
+    show_defaults: bool,
+    show_env_vars: bool,
+    show_possible_values: bool,
+}
+
+/// Configures a variable of `OwnedParser`, similarly to `Ref`
+pub struct OwnedRef<'a, T: 'static> {
+    parser: &'a mut OwnedParser,
+    index: usize,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: 'static + Clone> OwnedRef<'a, T> {
+
+    fn variable(&mut self) -> &mut TypedVariable<T> {
+        let item = self.parser.variables[self.index];
+        match self.parser.items[item] {
+            Item::Variable(ref mut var) => {
+                return var.as_any_mut().downcast_mut::<TypedVariable<T>>()
+                    .unwrap();
+            }
+            Item::Flag(..) | Item::ColorOption | Item::Group(..) => {
+                unreachable!();
+            }
+        }
+    }
+
+    fn push(&mut self, setting: Setting<T>) {
+        self.variable().settings.push(setting);
+    }
+
+    pub fn add_option<'x, A>(&'x mut self, names: &[&str], action: A,
+        help: &str)
+        -> &'x mut OwnedRef<'a, T>
+        where A: TypedAction<T> + 'static
+    {
+        self.push(Setting::Option(
+            names.iter().map(|x| x.to_string()).collect(),
+            Box::new(action), help.to_string()));
+        return self;
+    }
+
+    pub fn add_argument<'x, A>(&'x mut self, name: &str, action: A,
+        help: &str)
+        -> &'x mut OwnedRef<'a, T>
+        where A: TypedAction<T> + 'static
+    {
+        self.push(Setting::Argument(name.to_string(), Box::new(action),
+            help.to_string()));
+        return self;
+    }
+
+    /// See `Ref::config_key`
+    pub fn config_key<'x, A>(&'x mut self, key: &str, action: A)
+        -> &'x mut OwnedRef<'a, T>
+        where A: TypedAction<T> + 'static
+    {
+        self.push(Setting::ConfigKey(key.to_string(), Box::new(action)));
+        return self;
+    }
+
+    pub fn metavar<'x>(&'x mut self, name: &str)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::Metavar(name.to_string()));
+        return self;
+    }
+
+    pub fn completion<'x>(&'x mut self, completion: Completion)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::Completion(completion));
+        return self;
+    }
+
+    pub fn nargs<'x, A: Into<Arity>>(&'x mut self, arity: A)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::Nargs(arity.into()));
+        return self;
+    }
+
+    pub fn delimiter<'x>(&'x mut self, delimiter: char)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::Delimiter(delimiter));
+        return self;
+    }
+
+    pub fn required<'x>(&'x mut self) -> &'x mut OwnedRef<'a, T> {
+        self.push(Setting::Required);
+        return self;
+    }
+
+    pub fn requires<'x>(&'x mut self, name: &str)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::Requires(name.to_string()));
+        return self;
+    }
+
+    pub fn conflicts_with<'x>(&'x mut self, name: &str)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::ConflictsWith(name.to_string()));
+        return self;
+    }
+
+    pub fn required_if<'x>(&'x mut self, name: &str)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::RequiredIf(name.to_string()));
+        return self;
+    }
+
+    /// Returns handle which is used to get value after parsing
+    pub fn handle(&self) -> Handle<T> {
+        return Handle { index: self.index, marker: PhantomData };
+    }
+}
+
+impl<'a> OwnedRef<'a, bool> {
+    /// See `Ref::add_negatable_option`
+    pub fn add_negatable_option<'x>(&'x mut self, names: &[&str],
+        help: &str)
+        -> &'x mut OwnedRef<'a, bool>
+    {
+        self.push(Setting::NegatableOption(
+            names.iter().map(|x| x.to_string()).collect(),
+            help.to_string(), add_negatable_option));
+        return self;
+    }
+}
+
+impl<'a, T: 'static + Clone + FromStr> OwnedRef<'a, Vec<T>> {
+    /// See `Ref::envvar_list`
+    pub fn envvar_list<'x>(&'x mut self, varname: &str)
+        -> &'x mut OwnedRef<'a, Vec<T>>
+    {
+        self.push(Setting::EnvVar(varname.to_string(), add_envvar_list::<T>));
+        return self;
+    }
+}
+
+impl<'a, T: 'static + Clone + Display> OwnedRef<'a, T> {
+    /// See `Ref::show_default`
+    pub fn show_default<'x>(&'x mut self) -> &'x mut OwnedRef<'a, T> {
+        let value = format!("{}", self.variable().default);
+        self.push(Setting::Default(value));
+        return self;
+    }
+}
+
+impl<'a, T: 'static + Clone + Debug> OwnedRef<'a, T> {
+    /// See `Ref::show_default_debug`
+    pub fn show_default_debug<'x>(&'x mut self) -> &'x mut OwnedRef<'a, T> {
+        let value = format!("{:?}", self.variable().default);
+        self.push(Setting::Default(value));
+        return self;
+    }
+}
+
+impl<'a, T: 'static + Clone + FromStr> OwnedRef<'a, T> {
+    pub fn envvar<'x>(&'x mut self, varname: &str)
+        -> &'x mut OwnedRef<'a, T>
+    {
+        self.push(Setting::EnvVar(varname.to_string(), add_envvar::<T>));
+        return self;
+    }
+}
+
+impl OwnedParser {
+
+    /// Create an empty argument parser
+    pub fn new() -> OwnedParser {
+        return OwnedParser {
+            description: String::new(),
+            epilog: String::new(),
+            examples: Vec::new(),
+            sections: Vec::new(),
+            items: Vec::new(),
+            variables: Vec::new(),
+            exclusive_groups: Vec::new(),
+            hidden_options: Vec::new(),
+            deprecated_options: Vec::new(),
+            stop_on_first_argument: false,
+            silence_double_dash: true,
+            response_files: ResponseFiles::Disabled,
+            config: None,
+            color: ColorChoice::Auto,
+            theme: Theme::default(),
+            width: None,
+            option_width: None,
+            show_defaults: true,
+            show_env_vars: true,
+            show_possible_values: true,
+        };
+    }
+
+    /// Declare a variable with the default value
+    ///
+    /// This returns `OwnedRef` object which should be used configure the
+    /// option, and `OwnedRef::handle` to get the value after parsing
+    pub fn add_var<'x, T: 'static + Clone>(&'x mut self, default: T)
+        -> OwnedRef<'x, T>
+    {
+        let index = self.variables.len();
+        self.variables.push(self.items.len());
+        self.items.push(Item::Variable(Box::new(TypedVariable {
+            default: default,
+            settings: Vec::new(),
+        })));
+        return OwnedRef {
+            parser: self,
+            index: index,
+            marker: PhantomData,
+        };
+    }
+
+    /// Add option to argument parser
+    ///
+    /// This is only useful for options that don't store value. For
+    /// example `Print(...)`
+    pub fn add_option<F: IFlagAction + 'static>(&mut self,
+        names: &[&str], action: F, help: &str)
+    {
+        self.items.push(Item::Flag(
+            names.iter().map(|x| x.to_string()).collect(),
+            Box::new(action), help.to_string()));
+    }
+
+    /// Set description of the command
+    pub fn set_description(&mut self, descr: &str) {
+        self.description = descr.to_string();
+    }
+
+    /// See `ArgumentParser::set_epilog`
+    pub fn set_epilog(&mut self, epilog: &str) {
+        self.epilog = epilog.to_string();
+    }
+
+    /// See `ArgumentParser::add_example`
+    pub fn add_example(&mut self, description: &str, command: &str) {
+        self.examples.push((description.to_string(), command.to_string()));
+    }
+
+    /// See `ArgumentParser::add_section`
+    pub fn add_section(&mut self, title: &str, text: &str) {
+        self.sections.push((title.to_string(), text.to_string(), false));
+    }
+
+    /// See `ArgumentParser::add_verbatim_section`
+    pub fn add_verbatim_section(&mut self, title: &str, text: &str) {
+        self.sections.push((title.to_string(), text.to_string(), true));
+    }
+
+    /// See `ArgumentParser::add_group`
+    pub fn add_group(&mut self, title: &str, description: &str) {
+        self.items.push(Item::Group(title.to_string(),
+                                    description.to_string()));
+    }
+
+    /// See `ArgumentParser::add_exclusive_group`
+    pub fn add_exclusive_group(&mut self, names: &[&str], required: bool) {
+        self.exclusive_groups.push(
+            (names.iter().map(|x| x.to_string()).collect(), required));
+    }
+
+    /// See `ArgumentParser::hide_option`
+    pub fn hide_option(&mut self, name: &str) {
+        self.hidden_options.push(name.to_string());
+    }
+
+    /// See `ArgumentParser::deprecate_option`
+    pub fn deprecate_option(&mut self, name: &str, message: &str) {
+        self.deprecated_options.push((name.to_string(), message.to_string()));
+    }
+
+    /// See `ArgumentParser::stop_on_first_argument`
+    pub fn stop_on_first_argument(&mut self, want_stop: bool) {
+        self.stop_on_first_argument = want_stop;
+    }
+
+    /// See `ArgumentParser::silence_double_dash`
+    pub fn silence_double_dash(&mut self, silence: bool) {
+        self.silence_double_dash = silence;
+    }
+
+    /// See `ArgumentParser::set_config`
+    pub fn set_config(&mut self, config: Config) {
+        self.config = Some(config);
+    }
+
+    /// See `ArgumentParser::response_files`
+    pub fn response_files(&mut self, mode: ResponseFiles) {
+        self.response_files = mode;
+    }
+
+    /// See `ArgumentParser::set_color`
+    pub fn set_color(&mut self, choice: ColorChoice) {
+        self.color = choice;
+    }
+
+    /// See `ArgumentParser::show_defaults`
+    pub fn show_defaults(&mut self, show: bool) {
+        self.show_defaults = show;
+    }
+
+    /// See `ArgumentParser::show_env_vars`
+    pub fn show_env_vars(&mut self, show: bool) {
+        self.show_env_vars = show;
+    }
+
+    /// See `ArgumentParser::show_possible_values`
+    pub fn show_possible_values(&mut self, show: bool) {
+        self.show_possible_values = show;
+    }
+
+    /// See `ArgumentParser::set_theme`
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// See `ArgumentParser::set_width`
+    pub fn set_width(&mut self, width: usize) {
+        self.width = Some(width);
+    }
+
+    /// See `ArgumentParser::set_option_width`
+    pub fn set_option_width(&mut self, width: usize) {
+        self.option_width = Some(width);
+    }
+
+    /// See `ArgumentParser::add_color_option`
+    pub fn add_color_option(&mut self) {
+        self.items.push(Item::ColorOption);
+    }
+
+    fn defaults(&self) -> Vec<Box<Any>> {
+        let mut values = Vec::new();
+        for item in self.items.iter() {
+            match *item {
+                Item::Variable(ref var) => values.push(var.default_value()),
+                Item::Flag(..) | Item::ColorOption | Item::Group(..) => {}
+            }
+        }
+        return values;
+    }
+
+    fn build<'p>(&'p self, values: &'p mut Vec<Box<Any>>)
+        -> ArgumentParser<'p>
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description(&self.description);
+        ap.set_epilog(&self.epilog);
+        for &(ref description, ref command) in self.examples.iter() {
+            ap.add_example(description, command);
+        }
+        for &(ref title, ref text, verbatim) in self.sections.iter() {
+            if verbatim {
+                ap.add_verbatim_section(title, text);
+            } else {
+                ap.add_section(title, text);
+            }
+        }
+        ap.stop_on_first_argument(self.stop_on_first_argument);
+        ap.silence_double_dash(self.silence_double_dash);
+        ap.response_files(self.response_files);
+        ap.set_color(self.color);
+        ap.set_theme(self.theme.clone());
+        ap.width = self.width;
+        ap.option_width = self.option_width;
+        ap.show_defaults(self.show_defaults);
+        ap.show_env_vars(self.show_env_vars);
+        ap.show_possible_values(self.show_possible_values);
+        match self.config {
+            Some(ref config) => ap.set_config(config),
+            None => {}
+        }
+        let mut values = values.iter_mut();
+        for item in self.items.iter() {
+            match *item {
+                Item::Variable(ref var) => {
+                    var.declare(&mut ap, &mut **values.next().unwrap());
+                }
+                Item::Flag(ref names, ref action, ref help) => {
+                    let names: Vec<_> = names.iter().map(|x| &x[..])
+                        .collect();
+                    ap.add_option(&names, SharedFlag(&**action), help);
+                }
+                Item::ColorOption => ap.add_color_option(),
+                Item::Group(ref title, ref description) => {
+                    ap.add_group(title, description);
+                }
+            }
+        }
+        for &(ref names, required) in self.exclusive_groups.iter() {
+            let names: Vec<_> = names.iter().map(|x| &x[..]).collect();
+            ap.add_exclusive_group(&names, required);
+        }
+        for name in self.hidden_options.iter() {
+            ap.hide_option(name);
+        }
+        for &(ref name, ref message) in self.deprecated_options.iter() {
+            ap.deprecate_option(name, message);
+        }
+        return ap;
+    }
+
+    fn values(values: Vec<Box<Any>>) -> Values {
+        return Values {
+            values: values.into_iter().map(Some).collect(),
+        };
+    }
+
+    /// Print help
+    pub fn print_help(&self, name: &str, writer: &mut Write) -> IoResult<()> {
+        let mut values = self.defaults();
+        return self.build(&mut values).print_help(name, writer);
+    }
+
+    /// Print usage
+    pub fn print_usage(&self, name: &str, writer: &mut Write)
+        -> IoResult<()>
+    {
+        let mut values = self.defaults();
+        return self.build(&mut values).print_usage(name, writer);
+    }
+
+    /// Write man page, see `ArgumentParser::print_man_page`
+    pub fn print_man_page(&self, name: &str, section: &str,
+        writer: &mut Write)
+        -> IoResult<()>
+    {
+        let mut values = self.defaults();
+        return self.build(&mut values).print_man_page(name, section, writer);
+    }
+
+    /// Write shell completion script, see
+    /// `ArgumentParser::print_completion`
+    pub fn print_completion(&self, shell: Shell, name: &str,
+        writer: &mut Write)
+        -> IoResult<()>
+    {
+        let mut values = self.defaults();
+        return self.build(&mut values).print_completion(shell, name, writer);
+    }
+
+    /// Parse arguments
+    ///
+    /// Returns values of all variables on success, or the exit code
+    /// just like `ArgumentParser::parse`
+    pub fn parse(&self, args: Vec<String>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<Values, i32>
+    {
+        let mut values = self.defaults();
+        try!(self.build(&mut values).parse(args, stdout, stderr));
+        return Ok(OwnedParser::values(values));
+    }
+
+    /// Parse arguments and return a structured error
+    ///
+    /// See `ArgumentParser::try_parse`
+    pub fn try_parse(&self, args: Vec<String>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<Values, ParseError>
+    {
+        let mut values = self.defaults();
+        try!(self.build(&mut values).try_parse(args, stdout, stderr));
+        return Ok(OwnedParser::values(values));
+    }
+
+    /// Parse arguments which are not necessarily valid UTF-8
+    ///
+    /// See `ArgumentParser::parse_os`
+    pub fn parse_os(&self, args: Vec<OsString>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<Values, i32>
+    {
+        let mut values = self.defaults();
+        try!(self.build(&mut values).parse_os(args, stdout, stderr));
+        return Ok(OwnedParser::values(values));
+    }
+
+    /// Parse arguments which are not necessarily valid UTF-8 and return a
+    /// structured error
+    ///
+    /// See `ArgumentParser::try_parse_os`
+    pub fn try_parse_os(&self, args: Vec<OsString>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<Values, ParseError>
+    {
+        let mut values = self.defaults();
+        try!(self.build(&mut values).try_parse_os(args, stdout, stderr));
+        return Ok(OwnedParser::values(values));
+    }
+
+    /// See `ArgumentParser::try_parse_provenance`
+    pub fn try_parse_provenance(&self, args: Vec<String>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<(Values, Provenance), ParseError>
+    {
+        let mut values = self.defaults();
+        let prov = try!(self.build(&mut values)
+            .try_parse_provenance(args, stdout, stderr));
+        return Ok((OwnedParser::values(values), prov));
+    }
+
+    /// Convenience method to parse arguments
+    ///
+    /// On error returns error code that is supposed to be returned by
+    /// an application. (i.e. zero on `--help` and `2` on argument error)
+    pub fn parse_args(&self) -> Result<Values, i32> {
+        let mut values = self.defaults();
+        try!(self.build(&mut values).parse_terminal(env::args_os().collect()));
+        return Ok(OwnedParser::values(values));
+    }
+
+    /// The simplest conveninece method
+    ///
+    /// The method returns only in case of successful parsing or exits with
+    /// appropriate code (including successful on `--help`) otherwise.
+    pub fn parse_args_or_exit(&self) -> Values {
+        return self.parse_args().unwrap_or_else(|c| exit(c));
+    }
+}
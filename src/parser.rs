@@ -1,9 +1,12 @@
+use std::cmp::max;
+use std::fmt::{Debug, Display};
 use std::env;
+use std::ffi::{OsStr, OsString};
 use std::io::{Write};
 use std::io::Result as IoResult;
-use std::io::{stdout, stderr};
+use std::io::{stdout, stderr, IsTerminal};
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::iter::Peekable;
 use std::slice::Iter;
 use std::hash::Hash;
@@ -19,12 +22,25 @@ use std::collections::hash_map::Entry;
 use std::collections::HashSet;
 
 use super::action::{Action, ParseResult};
-use super::action::ParseResult::{Parsed, Help, Exit, Error};
+use super::action::ParseResult::{Parsed, Help, Exit, Error, BadValue};
+use super::action::ParseResult::WrongNumber;
 use super::action::TypedAction;
-use super::action::Action::{Flag, Single, Push, Many};
+use super::action::Action::{Flag, Single, Optional, Push, Many};
 use super::action::IArgAction;
-use super::generic::StoreAction;
-use super::help::{HelpAction, wrap_text};
+use super::action::Arity;
+use super::generic::{StoreAction, StoreConstAction};
+use super::Collect;
+use super::bool::normalize as normalize_bool;
+use super::help::{HelpAction, wrap_text, wrap_paragraphs, dedent};
+use super::error::ParseError;
+use super::completion::{Completion, Shell, CompletionFormatter};
+use super::man::ManFormatter;
+use super::suggest::did_you_mean;
+use super::response::{self, ResponseFiles};
+use super::config::Config;
+use super::origin::{Source, Origin, VarOrigin, Provenance};
+use super::style::{ColorChoice, ColorAction, Theme};
+use super::terminal;
 use action::IFlagAction;
 
 use self::ArgumentKind::{Positional, ShortOption, LongOption, Delimiter};
@@ -62,26 +78,111 @@ impl ArgumentKind {
     }
 }
 
-struct GenericArgument<'parser> {
-    id: usize,
-    varid: usize,
-    name: &'parser str,
-    help: &'parser str,
-    action: Action<'parser>,
+/// The part of the `arg` starting at byte `start`
+///
+/// The `start` must be right after an ASCII character or a valid UTF-8
+/// prefix of the `arg` (which is always the case after an option name)
+pub(crate) fn os_str_tail(arg: &OsStr, start: usize) -> &OsStr {
+    let bytes = arg.as_encoded_bytes();
+    // Safe because of the requirement above
+    return unsafe { OsStr::from_encoded_bytes_unchecked(&bytes[start..]) };
+}
+
+/// Split the `value` by the `delimiter`
+///
+/// A backslash escapes the delimiter or another backslash, and is kept
+/// as is before any other character
+fn split_list(value: &OsStr, delimiter: char) -> Vec<OsString> {
+    let mut buf = [0; 4];
+    let delimiter = delimiter.encode_utf8(&mut buf).as_bytes();
+    let bytes = value.as_encoded_bytes();
+    let mut items = Vec::new();
+    let mut item = Vec::new();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let rest = &bytes[idx..];
+        if rest.starts_with(b"\\\\") {
+            item.push(b'\\');
+            idx += 2;
+        } else if rest[0] == b'\\' && rest[1..].starts_with(delimiter) {
+            item.extend_from_slice(delimiter);
+            idx += 1 + delimiter.len();
+        } else if rest.starts_with(delimiter) {
+            items.push(item);
+            item = Vec::new();
+            idx += delimiter.len();
+        } else {
+            item.push(rest[0]);
+            idx += 1;
+        }
+    }
+    items.push(item);
+    return items.into_iter().map(|item| {
+        // Safe because only ASCII backslashes are removed and the value is
+        // split at a valid UTF-8 character
+        unsafe { OsString::from_encoded_bytes_unchecked(item) }
+    }).collect();
 }
 
-struct GenericOption<'parser> {
-    id: usize,
-    varid: Option<usize>,
-    names: Vec<&'parser str>,
-    help: &'parser str,
-    action: Action<'parser>,
+pub(crate) struct GenericArgument<'parser> {
+    pub(crate) id: usize,
+    pub(crate) varid: usize,
+    pub(crate) name: &'parser str,
+    pub(crate) help: &'parser str,
+    pub(crate) action: Action<'parser>,
 }
 
-struct EnvVar<'parser> {
-    varid: usize,
-    name: &'parser str,
-    action: Box<IArgAction + 'parser>,
+pub(crate) struct GenericOption<'parser> {
+    pub(crate) id: usize,
+    pub(crate) varid: Option<usize>,
+    pub(crate) names: Vec<&'parser str>,
+    pub(crate) help: &'parser str,
+    pub(crate) action: Action<'parser>,
+    /// Index in `ArgumentParser::help_groups`
+    pub(crate) group: Option<usize>,
+}
+
+pub(crate) struct HelpGroup<'parser> {
+    pub(crate) title: &'parser str,
+    pub(crate) description: &'parser str,
+}
+
+pub(crate) struct HelpSection<'parser> {
+    pub(crate) title: &'parser str,
+    pub(crate) text: &'parser str,
+    /// Written as is (dedented) instead of being wrapped
+    pub(crate) verbatim: bool,
+}
+
+pub(crate) struct Example<'parser> {
+    pub(crate) description: &'parser str,
+    pub(crate) command: &'parser str,
+}
+
+pub(crate) struct EnvVar<'parser> {
+    pub(crate) varid: usize,
+    pub(crate) name: &'parser str,
+    /// `Single` for `Ref::envvar`, a list action for `Ref::envvar_list`
+    pub(crate) action: Action<'parser>,
+}
+
+pub(crate) struct ConfigKey<'parser> {
+    pub(crate) varid: usize,
+    pub(crate) key: &'parser str,
+    pub(crate) action: Action<'parser>,
+}
+
+pub(crate) struct Subcommand<'parser> {
+    pub(crate) varid: usize,
+    pub(crate) name: &'parser str,
+    pub(crate) help: &'parser str,
+    pub(crate) parser: ArgumentParser<'parser>,
+    pub(crate) action: Box<IArgAction + 'parser>,
+}
+
+pub(crate) struct ExclusiveGroup {
+    pub(crate) options: Vec<usize>,
+    pub(crate) required: bool,
 }
 
 impl<'a> Hash for GenericOption<'a> {
@@ -113,9 +214,22 @@ impl<'a> PartialEq for GenericArgument<'a> {
 impl<'a> Eq for GenericArgument<'a> {}
 
 pub struct Var {
-    id: usize,
-    metavar: String,
-    required: bool,
+    pub(crate) id: usize,
+    pub(crate) metavar: String,
+    pub(crate) required: bool,
+    pub(crate) completion: Completion,
+    pub(crate) requires: Vec<String>,
+    pub(crate) conflicts_with: Vec<String>,
+    pub(crate) required_if: Vec<String>,
+    /// Default value shown in help, see `Ref::show_default`
+    pub(crate) default: Option<String>,
+    /// Environment and configuration values are normalized as booleans,
+    /// see `Ref::add_negatable_option`
+    pub(crate) boolean: bool,
+    /// Number of values of list options and arguments, see `Ref::nargs`
+    pub(crate) arity: Option<Arity>,
+    /// Values of list options are split by it, see `Ref::delimiter`
+    pub(crate) delimiter: Option<char>,
 }
 
 impl Hash for Var {
@@ -135,49 +249,182 @@ impl Eq for Var {}
 struct Context<'ctx, 'parser: 'ctx> {
     parser: &'ctx ArgumentParser<'parser>,
     set_vars: HashSet<usize>,
-    list_options: HashMap<Rc<GenericOption<'parser>>, Vec<&'ctx str>>,
-    list_arguments: HashMap<Rc<GenericArgument<'parser>>, Vec<&'ctx str>>,
-    arguments: Vec<&'ctx str>,
-    iter: Peekable<Iter<'ctx, String>>,
+    /// Subset of `set_vars` that were set from the config, these are
+    /// still overridden by positional arguments
+    config_vars: HashSet<usize>,
+    used_options: HashMap<usize, String>,
+    deprecation_warned: HashSet<String>,
+    origins: HashMap<usize, Vec<Origin>>,
+    list_options: HashMap<Rc<GenericOption<'parser>>, Vec<&'ctx OsStr>>,
+    list_arguments: HashMap<Rc<GenericArgument<'parser>>, Vec<&'ctx OsStr>>,
+    arguments: Vec<&'ctx OsStr>,
+    iter: Peekable<Iter<'ctx, OsString>>,
     stderr: &'ctx mut (Write + 'ctx),
+    subcommand: Option<&'ctx Subcommand<'parser>>,
+    subcommand_args: Vec<OsString>,
 }
 
 impl<'a, 'b> Context<'a, 'b> {
 
-    fn parse_option(&mut self, opt: Rc<GenericOption<'b>>,
-        optarg: Option<&'a str>)
-        -> ParseResult
+    fn action_result(&self, res: ParseResult, name: &str,
+        varid: Option<usize>)
+        -> Result<(), ParseError>
+    {
+        match res {
+            Parsed => Ok(()),
+            Help => Err(ParseError::Help),
+            Exit => Err(ParseError::Exit),
+            Error(message) => Err(ParseError::Custom(message)),
+            WrongNumber(arity, got) => {
+                Err(ParseError::WrongNumberOfValues {
+                    name: name.to_string(),
+                    arity: arity,
+                    got: got,
+                })
+            }
+            BadValue(value, reason) => {
+                let var = varid.map(|varid| &self.parser.vars[varid]);
+                let suggestion = match var.map(|var| &var.completion) {
+                    Some(&Completion::Values(ref values)) => {
+                        did_you_mean(&value,
+                            values.iter().map(|x| &x[..]))
+                    }
+                    _ => None,
+                };
+                Err(ParseError::BadValue {
+                    name: name.to_string(),
+                    value: value,
+                    metavar: match var {
+                        Some(var) => var.metavar.clone(),
+                        None => String::new(),
+                    },
+                    reason: reason,
+                    suggestion: suggestion,
+                })
+            }
+        }
+    }
+
+    /// Record where the value of the variable came from
+    ///
+    /// If `merge` is true and the previous value came from the same
+    /// source, the value is appended to it (used for lists and flags)
+    fn record(&mut self, varid: usize, source: Source, name: &str,
+        value: Option<&OsStr>, merge: bool)
+    {
+        let origins = self.origins.entry(varid).or_insert_with(Vec::new);
+        match origins.last_mut() {
+            Some(ref mut last) if merge
+                && last.source == source && last.name == name
+            => {
+                last.values.extend(
+                    value.map(|v| v.to_string_lossy().into_owned()));
+                return;
+            }
+            _ => {}
+        }
+        origins.push(Origin {
+            source: source,
+            name: name.to_string(),
+            values: value.map(|v| v.to_string_lossy().into_owned())
+                .into_iter().collect(),
+        });
+    }
+
+    fn option_used(&mut self, opt: &GenericOption<'b>, name: &str,
+        value: Option<&OsStr>)
+    {
+        match opt.varid {
+            Some(varid) => {
+                self.set_vars.insert(varid);
+                let merge = match opt.action {
+                    Single(_) | Optional(..) => false,
+                    Flag(_) | Push(_) | Many(_) => true,
+                };
+                self.record(varid, Source::CommandLine, name, value, merge);
+            }
+            None => {}
+        }
+        self.used_options.entry(opt.id)
+            .or_insert_with(|| name.to_string());
+        match self.parser.deprecated_options.get(name) {
+            Some(message) if !self.deprecation_warned.contains(name) => {
+                self.deprecation_warned.insert(name.to_string());
+                if message.is_empty() {
+                    writeln!(self.stderr,
+                        "WARNING: Option {} is deprecated", name).ok();
+                } else {
+                    writeln!(self.stderr,
+                        "WARNING: Option {} is deprecated: {}",
+                        name, message).ok();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the next argument may be a value of an optional-value option
+    fn next_is_value(&mut self) -> bool {
+        match self.iter.peek() {
+            Some(arg) => match ArgumentKind::check(&arg.to_string_lossy()) {
+                Positional => return true,
+                ShortOption | LongOption | Delimiter => return false,
+            },
+            None => return false,
+        }
+    }
+
+    fn parse_option(&mut self, opt: Rc<GenericOption<'b>>, name: &str,
+        optarg: Option<&'a OsStr>)
+        -> Result<(), ParseError>
     {
+        match opt.action {
+            Push(_) | Many(_) => {
+                match self.parser.vars[opt.varid.unwrap()].arity {
+                    Some(arity) => {
+                        return self.parse_values(opt.clone(), name, optarg,
+                                                 arity);
+                    }
+                    None => {}
+                }
+            }
+            Flag(_) | Single(_) | Optional(..) => {}
+        }
         let value = match optarg {
             Some(value) => value,
-            None => match self.iter.next() {
-                Some(value) => {
-                    &value[..]
-                }
-                None => {
-                    return match opt.action {
-                        Many(_) => Parsed,
-                        _ => Error(format!(
-                            // TODO(tailhook) is {:?} ok?
-                            "Option {:?} requires an argument", opt.names)),
-                    };
+            None => match opt.action {
+                Optional(_, ref action) if !self.next_is_value() => {
+                    self.option_used(&opt, name, None);
+                    let res = action.parse_flag();
+                    return self.action_result(res, name, opt.varid);
                 }
+                _ => match self.iter.next() {
+                    Some(value) => {
+                        value.as_os_str()
+                    }
+                    None => {
+                        return match opt.action {
+                            Many(_) => Ok(()),
+                            _ => Err(ParseError::MissingValue(
+                                opt.names.iter()
+                                .map(|x| x.to_string()).collect())),
+                        };
+                    }
+                },
             },
         };
-        match opt.varid {
-            Some(varid) => { self.set_vars.insert(varid); }
-            None => {}
-        }
+        self.option_used(&opt, name, Some(value));
         match opt.action {
-            Single(ref action) => {
-                return action.parse_arg(value);
+            Single(ref action) | Optional(ref action, _) => {
+                let res = action.parse_os_arg(value);
+                return self.action_result(res, name, opt.varid);
             }
             Push(_) => {
                 (match self.list_options.entry(opt.clone()) {
                     Entry::Occupied(occ) => occ.into_mut(),
                     Entry::Vacant(vac) => vac.insert(Vec::new()),
                 }).push(value);
-                return Parsed;
+                return Ok(());
             }
             Many(_) => {
                 let vec = match self.list_options.entry(opt.clone()) {
@@ -186,105 +433,170 @@ impl<'a, 'b> Context<'a, 'b> {
                 };
                 vec.push(value);
                 match optarg {
-                    Some(_) => return Parsed,
+                    Some(_) => return Ok(()),
                     _ => {}
                 }
+                let start = vec.len();
                 loop {
                     match self.iter.peek() {
                         None => { break; }
-                        Some(arg) if arg.starts_with('-') => {
+                        Some(arg) if arg.as_encoded_bytes().starts_with(b"-")
+                        => {
                             break;
                         }
                         Some(value) => {
-                            vec.push(&value[..]);
+                            vec.push(value.as_os_str());
                         }
                     }
                     self.iter.next();
                 }
-                return Parsed;
+                let values = vec[start..].to_vec();
+                for value in values {
+                    self.record(opt.varid.unwrap(), Source::CommandLine,
+                        name, Some(value), true);
+                }
+                return Ok(());
             }
             _ => panic!(),
         };
     }
 
-    fn parse_long_option(&mut self, arg: &'a str) -> ParseResult {
-        let mut equals_iter = arg.splitn(2, '=');
-        let optname = equals_iter.next().unwrap();
-        let valueref = equals_iter.next();
-        let opt = self.parser.long_options.get(&optname.to_string());
+    /// Parse values of a list option having arity
+    ///
+    /// The attached value (`--opt=value`) is the first one. Values are
+    /// taken until the maximum number or the next option. Values are
+    /// counted after splitting by the delimiter (see `Ref::delimiter`).
+    fn parse_values(&mut self, opt: Rc<GenericOption<'b>>, name: &str,
+        optarg: Option<&'a OsStr>, arity: Arity)
+        -> Result<(), ParseError>
+    {
+        let varid = opt.varid.unwrap();
+        let mut values: Vec<&'a OsStr> = optarg.into_iter().collect();
+        let mut count = self.parser.split_values(varid, &values).len();
+        while count < arity.max && self.next_is_value() {
+            let value = self.iter.next().unwrap().as_os_str();
+            count += self.parser.split_value(varid, value).len();
+            values.push(value);
+        }
+        if !arity.contains(count) {
+            return Err(ParseError::WrongNumberOfValues {
+                name: name.to_string(),
+                arity: arity,
+                got: count,
+            });
+        }
+        self.option_used(&opt, name, values.first().map(|x| *x));
+        for value in values.iter().skip(1) {
+            self.record(opt.varid.unwrap(), Source::CommandLine,
+                name, Some(*value), true);
+        }
+        (match self.list_options.entry(opt.clone()) {
+            Entry::Occupied(occ) => occ.into_mut(),
+            Entry::Vacant(vac) => vac.insert(Vec::new()),
+        }).extend(values);
+        return Ok(());
+    }
+
+    fn parse_long_option(&mut self, arg: &'a OsStr)
+        -> Result<(), ParseError>
+    {
+        let bytes = arg.as_encoded_bytes();
+        let equals = bytes.iter().position(|&b| b == b'=');
+        let (optname, valueref) = match equals {
+            Some(idx) => {
+                (String::from_utf8_lossy(&bytes[..idx]),
+                 Some(os_str_tail(arg, idx+1)))
+            }
+            None => (String::from_utf8_lossy(bytes), None),
+        };
+        let optname = &optname[..];
+        let opt = self.parser.long_options.get(optname);
         match opt {
             Some(opt) => {
                 match opt.action {
                     Flag(ref action) => {
                         match valueref {
-                            Some(_) => {
-                                return Error(format!(
-                                    "Option {} does not accept an argument",
-                                    optname));
+                            Some(value) => {
+                                return Err(ParseError::UnexpectedValue {
+                                    option: optname.to_string(),
+                                    value: value.to_string_lossy()
+                                        .into_owned(),
+                                });
                             }
                             None => {
-                                match opt.varid {
-                                    Some(varid) => {
-                                        self.set_vars.insert(varid);
-                                    }
-                                    None => {}
-                                }
-                                return action.parse_flag();
+                                self.option_used(opt, optname, None);
+                                let res = action.parse_flag();
+                                return self.action_result(res,
+                                    optname, opt.varid);
                             }
                         }
                     }
-                    Single(_) | Push(_) | Many(_) => {
-                        return self.parse_option(opt.clone(), valueref);
+                    Single(_) | Optional(..) | Push(_) | Many(_) => {
+                        return self.parse_option(opt.clone(), optname,
+                            valueref);
                     }
                 }
             }
             None => {
-                return Error(format!("Unknown option {}", arg));
+                return Err(ParseError::UnknownOption {
+                    option: arg.to_string_lossy().into_owned(),
+                    suggestion: did_you_mean(optname,
+                        self.parser.long_names().iter().map(|x| &x[..])),
+                });
             }
         }
     }
 
-    fn parse_short_options<'x>(&'x mut self, arg: &'a str) -> ParseResult {
-        let mut iter = arg.char_indices();
+    fn parse_short_options<'x>(&'x mut self, arg: &'a OsStr)
+        -> Result<(), ParseError>
+    {
+        let text = arg.to_string_lossy();
+        let mut iter = text.char_indices();
         iter.next();
         for (idx, ch) in iter {
+            let name = format!("-{}", ch);
             let opt = match self.parser.short_options.get(&ch) {
                 Some(opt) => { opt }
                 None => {
-                    return Error(format!("Unknown short option \"{}\"", ch));
+                    // Probably a long option with a single dash: -verbose
+                    let long = format!("-{}",
+                        text.splitn(2, '=').next().unwrap());
+                    return Err(ParseError::UnknownOption {
+                        option: name,
+                        suggestion: if text.len() > 2 {
+                            did_you_mean(&long, self.parser.long_names()
+                                .iter().map(|x| &x[..]))
+                        } else {
+                            None
+                        },
+                    });
                 }
             };
-            let res = match opt.action {
+            match opt.action {
                 Flag(ref action) => {
-                    match opt.varid {
-                        Some(varid) => { self.set_vars.insert(varid); }
-                        None => {}
-                    }
-                    action.parse_flag()
+                    self.option_used(opt, &name, None);
+                    let res = action.parse_flag();
+                    try!(self.action_result(res, &name, opt.varid));
                 }
-                Single(_) | Push(_) | Many(_) => {
+                Single(_) | Optional(..) | Push(_) | Many(_) => {
                     let value;
                     if idx + 1 < arg.len() {
-                        value = Some(&arg[idx+1..arg.len()]);
+                        value = Some(os_str_tail(arg, idx+1));
                     } else {
                         value = None;
                     }
-                    return self.parse_option(opt.clone(), value);
+                    return self.parse_option(opt.clone(), &name, value);
                 }
-            };
-            match res {
-                Parsed => { continue; }
-                x => { return x; }
             }
         }
-        return Parsed;
+        return Ok(());
     }
 
-    fn postpone_argument(&mut self, arg: &'a str) {
+    fn postpone_argument(&mut self, arg: &'a OsStr) {
         self.arguments.push(arg);
     }
 
-    fn parse_options(&mut self) -> ParseResult {
+    fn parse_options(&mut self) -> Result<(), ParseError> {
         self.iter.next();  // Command name
         loop {
             let next = self.iter.next();
@@ -292,46 +604,80 @@ impl<'a, 'b> Context<'a, 'b> {
                 Some(arg) => { arg }
                 None => { break; }
             };
-            let res = match ArgumentKind::check(&arg[..]) {
+            match ArgumentKind::check(&arg.to_string_lossy()) {
+                Positional if !self.parser.subcommands.is_empty()
+                    && self.arguments.len() == self.parser.arguments.len()
+                => {
+                    return self.parse_subcommand(arg);
+                }
                 Positional => {
-                    self.postpone_argument(&arg[..]);
+                    self.postpone_argument(arg);
                     if self.parser.stop_on_first_argument {
                         break;
                     }
-                    continue;
                 }
-                LongOption => self.parse_long_option(&arg[..]),
-                ShortOption => self.parse_short_options(&arg[..]),
+                LongOption => try!(self.parse_long_option(arg)),
+                ShortOption => try!(self.parse_short_options(arg)),
                 Delimiter => {
                     if !self.parser.silence_double_dash {
-                        self.postpone_argument("--");
+                        self.postpone_argument(OsStr::new("--"));
                     }
                     break;
                 }
-            };
-            match res {
-                Parsed => continue,
-                _ => return res,
             }
         }
 
         loop {
             match self.iter.next() {
                 None => break,
-                Some(arg) => self.postpone_argument(&arg[..]),
+                Some(arg) => self.postpone_argument(arg),
+            }
+        }
+        return Ok(());
+    }
+
+    fn parse_subcommand(&mut self, arg: &'a OsStr)
+        -> Result<(), ParseError>
+    {
+        let name = &arg.to_string_lossy()[..];
+        let sub = match self.parser.subcommands.iter()
+            .find(|sub| sub.name == name)
+        {
+            Some(sub) => sub,
+            None => {
+                return Err(ParseError::UnknownCommand {
+                    command: name.to_string(),
+                    suggestion: did_you_mean(name,
+                        self.parser.subcommands.iter().map(|sub| sub.name)),
+                });
+            }
+        };
+        self.set_vars.insert(sub.varid);
+        self.record(sub.varid, Source::CommandLine, name, Some(arg), false);
+        let res = sub.action.parse_arg(name);
+        try!(self.action_result(res, name, Some(sub.varid)));
+        self.subcommand = Some(sub);
+        self.subcommand_args.push(arg.to_os_string());
+        loop {
+            match self.iter.next() {
+                None => break,
+                Some(arg) => self.subcommand_args.push(arg.clone()),
             }
         }
-        return Parsed;
+        return Ok(());
     }
 
-    fn parse_arguments(&mut self) -> ParseResult {
+    fn parse_arguments(&mut self) -> Result<(), ParseError> {
         let mut pargs = self.parser.arguments.iter();
-        for arg in self.arguments.iter() {
+        let arguments = self.arguments.clone();
+        for arg in arguments.iter() {
             let opt;
             loop {
                 match pargs.next() {
                     Some(option) => {
-                        if self.set_vars.contains(&option.varid) {
+                        if self.set_vars.contains(&option.varid) &&
+                            !self.config_vars.contains(&option.varid)
+                        {
                             continue;
                         }
                         opt = option;
@@ -342,93 +688,187 @@ impl<'a, 'b> Context<'a, 'b> {
                             opt = option;
                             break;
                         }
-                        None => return Error(format!(
-                            "Unexpected argument {}", arg)),
+                        None => return Err(ParseError::UnexpectedArgument(
+                            arg.to_string_lossy().into_owned())),
                     }
                 };
             }
-            let res = match opt.action {
-                Single(ref act) => {
+            match opt.action {
+                Single(ref act) | Optional(ref act, _) => {
                     self.set_vars.insert(opt.varid);
-                    act.parse_arg(*arg)
+                    self.record(opt.varid, Source::CommandLine, opt.name,
+                        Some(*arg), false);
+                    let res = act.parse_os_arg(*arg);
+                    try!(self.action_result(res, opt.name, Some(opt.varid)));
                 },
                 Many(_) | Push(_) => {
+                    self.set_vars.insert(opt.varid);
                     (match self.list_arguments.entry(opt.clone()) {
                         Entry::Occupied(occ) => occ.into_mut(),
                         Entry::Vacant(vac) => vac.insert(Vec::new()),
                     }).push(*arg);
-                    Parsed
+                    self.record(opt.varid, Source::CommandLine, opt.name,
+                        Some(*arg), true);
                 },
                 _ => unreachable!(),
-            };
-            match res {
-                Parsed => continue,
-                _ => return res,
             }
         }
-        return Parsed;
+        return Ok(());
     }
 
-    fn parse_list_vars(&mut self) -> ParseResult {
+    fn parse_list_vars(&mut self) -> Result<(), ParseError> {
         for (opt, lst) in self.list_options.iter() {
+            let values = self.parser.split_values(opt.varid.unwrap(), lst);
+            let values: Vec<_> = values.iter().map(|x| x.as_os_str())
+                .collect();
             match opt.action {
                 Push(ref act) | Many(ref act) => {
-                    let res = act.parse_args(&lst[..]);
-                    match res {
-                        Parsed => continue,
-                        _ => return res,
-                    }
+                    let res = act.parse_os_args(&values[..]);
+                    try!(self.action_result(res, opt.names[0], opt.varid));
                 }
                 _ => panic!(),
             }
         }
+        match self.parser.catchall_argument {
+            // A list argument not given on the command-line is checked
+            // too, unless the variable is set otherwise or it's required
+            // (reported by `check_required` then)
+            Some(ref opt) if !self.set_vars.contains(&opt.varid)
+                && !self.parser.vars[opt.varid].required
+                && !self.parser.accepts_values(opt, 0)
+            => {
+                return Err(ParseError::WrongNumberOfValues {
+                    name: opt.name.to_string(),
+                    arity: self.parser.vars[opt.varid].arity.unwrap(),
+                    got: 0,
+                });
+            }
+            _ => {}
+        }
         for (opt, lst) in self.list_arguments.iter() {
+            let values = self.parser.split_values(opt.varid, lst);
+            if !self.parser.accepts_values(opt, values.len()) {
+                return Err(ParseError::WrongNumberOfValues {
+                    name: opt.name.to_string(),
+                    arity: self.parser.vars[opt.varid].arity.unwrap(),
+                    got: values.len(),
+                });
+            }
+            let values: Vec<_> = values.iter().map(|x| x.as_os_str())
+                .collect();
             match opt.action {
                 Push(ref act) | Many(ref act) => {
-                    let res = act.parse_args(&lst[..]);
-                    match res {
-                        Parsed => continue,
-                        _ => return res,
-                    }
+                    let res = act.parse_os_args(&values[..]);
+                    try!(self.action_result(res, opt.name, Some(opt.varid)));
                 }
                 _ => panic!(),
             }
         }
-        return Parsed;
+        return Ok(());
     }
 
-    fn parse_env_vars(&mut self) -> ParseResult {
+    fn parse_env_vars(&mut self) -> Result<(), ParseError> {
         for evar in self.parser.env_vars.iter() {
-            match env::var(evar.name) {
-                Ok(val) => {
-                    match evar.action.parse_arg(&val[..]) {
-                        Parsed => {
+            match env::var_os(evar.name) {
+                Some(val) => {
+                    let val = if self.parser.vars[evar.varid].boolean {
+                        val.to_str().and_then(normalize_bool)
+                            .map(OsString::from).unwrap_or(val)
+                    } else {
+                        val
+                    };
+                    let res = match evar.action {
+                        Single(ref action) => action.parse_os_arg(&val),
+                        Push(ref action) | Many(ref action) => {
+                            let values = self.parser.split_value(
+                                evar.varid, &val);
+                            let values: Vec<_> = values.iter()
+                                .map(|x| x.as_os_str()).collect();
+                            action.parse_os_args(&values[..])
+                        }
+                        Flag(_) | Optional(..) => unreachable!(),
+                    };
+                    match self.action_result(res, evar.name, Some(evar.varid))
+                    {
+                        Ok(()) => {
                             self.set_vars.insert(evar.varid);
+                            self.record(evar.varid, Source::EnvVar,
+                                evar.name, Some(&val), false);
                             continue;
                         }
-                        Error(err) => {
+                        Err(err) => {
                             writeln!(self.stderr,
                                 "WARNING: Environment variable {}: {}",
                                 evar.name, err).ok();
                         }
-                        _ => unreachable!(),
                     }
                 }
-                Err(_) => {}
+                None => {}
+            }
+        }
+        return Ok(());
+    }
+
+    fn parse_config(&mut self) -> Result<(), ParseError> {
+        let config = match self.parser.config {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+        for ckey in self.parser.config_keys.iter() {
+            if self.set_vars.contains(&ckey.varid) {
+                continue;
+            }
+            match config.get(ckey.key) {
+                Some(val) => {
+                    let val = if self.parser.vars[ckey.varid].boolean {
+                        normalize_bool(val).unwrap_or(val)
+                    } else {
+                        val
+                    };
+                    let res = match ckey.action {
+                        Single(ref action) | Optional(ref action, _) => {
+                            action.parse_arg(val)
+                        }
+                        Push(ref action) | Many(ref action) => {
+                            let values = self.parser.split_value(
+                                ckey.varid, OsStr::new(val));
+                            let values: Vec<_> = values.iter()
+                                .map(|x| x.as_os_str()).collect();
+                            action.parse_os_args(&values[..])
+                        }
+                        Flag(_) => unreachable!(),
+                    };
+                    match self.action_result(res, ckey.key, Some(ckey.varid))
+                    {
+                        Ok(()) => {
+                            self.set_vars.insert(ckey.varid);
+                            self.config_vars.insert(ckey.varid);
+                            self.record(ckey.varid, Source::Config,
+                                ckey.key, Some(OsStr::new(val)), false);
+                        }
+                        Err(err) => {
+                            writeln!(self.stderr, "WARNING: Config {}: {}",
+                                config.location(ckey.key), err).ok();
+                        }
+                    }
+                }
+                None => {}
             }
         }
-        return Parsed;
+        return Ok(());
     }
 
-    fn check_required(&mut self) -> ParseResult {
+    fn check_required(&mut self) -> Result<(), ParseError> {
         // Check for required arguments
         for var in self.parser.vars.iter() {
             if var.required && !self.set_vars.contains(&var.id) {
                 // First try positional arguments
-                for opt in self.parser.arguments.iter() {
+                for opt in self.parser.arguments.iter()
+                    .chain(self.parser.catchall_argument.iter())
+                {
                     if opt.varid == var.id {
-                        return Error(format!(
-                            "Argument {} is required", opt.name));
+                        return Err(ParseError::MissingArgument(
+                            opt.name.to_string()));
                     }
                 }
                 // Then options
@@ -438,66 +878,223 @@ impl<'a, 'b> Context<'a, 'b> {
                         Some(varid) if varid == var.id => {}
                         _ => { continue }
                     }
-                    all_options.extend(opt.names.clone().into_iter());
+                    all_options.extend(
+                        opt.names.iter().map(|name| name.to_string()));
                 }
-                if all_options.len() > 1 {
-                    return Error(format!(
-                        "One of the options {:?} is required", all_options));
-                } else if all_options.len() == 1 {
-                    return Error(format!(
-                        "Option {:?} is required", all_options));
+                if !all_options.is_empty() {
+                    return Err(ParseError::MissingOption(all_options));
+                }
+                // Then subcommands
+                let commands: Vec<_> = self.parser.subcommands.iter()
+                    .filter(|sub| sub.varid == var.id)
+                    .map(|sub| sub.name.to_string())
+                    .collect();
+                if !commands.is_empty() {
+                    return Err(ParseError::MissingCommand(commands));
                 }
                 // Then envvars
                 for envvar in self.parser.env_vars.iter() {
                     if envvar.varid == var.id {
-                        return Error(format!(
-                            "Environment var {} is required", envvar.name));
+                        return Err(ParseError::MissingEnvVar(
+                            envvar.name.to_string()));
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /// Name of the variable for error messages
+    ///
+    /// It's the option name as written on the command-line if the option
+    /// is used, or the `fallback` otherwise
+    fn var_name(&self, varid: usize, fallback: &str) -> String {
+        for opt in self.parser.options.iter() {
+            if opt.varid == Some(varid) {
+                match self.used_options.get(&opt.id) {
+                    Some(name) => return name.clone(),
+                    None => {}
+                }
+            }
+        }
+        return fallback.to_string();
+    }
+
+    /// Name of the variable for error messages when it's not set
+    fn unset_var_name(&self, varid: usize) -> String {
+        for arg in self.parser.arguments.iter()
+            .chain(self.parser.catchall_argument.iter())
+        {
+            if arg.varid == varid {
+                return arg.name.to_string();
+            }
+        }
+        for opt in self.parser.options.iter() {
+            if opt.varid == Some(varid) {
+                let name = opt.names.iter().find(|x| x.starts_with("--"))
+                    .unwrap_or(&opt.names[0]);
+                return name.to_string();
+            }
+        }
+        return self.parser.vars[varid].metavar.clone();
+    }
+
+    fn check_constraints(&self) -> Result<(), ParseError> {
+        for var in self.parser.vars.iter() {
+            if self.set_vars.contains(&var.id) {
+                for name in var.requires.iter() {
+                    let other = self.parser.find_var(name);
+                    if !self.set_vars.contains(&other) {
+                        return Err(ParseError::MissingRequirement {
+                            option: self.var_name(var.id,
+                                &self.unset_var_name(var.id)),
+                            requirement: name.to_string(),
+                        });
+                    }
+                }
+                for name in var.conflicts_with.iter() {
+                    let other = self.parser.find_var(name);
+                    if self.set_vars.contains(&other) {
+                        return Err(ParseError::ConflictingOptions {
+                            option: self.var_name(var.id,
+                                &self.unset_var_name(var.id)),
+                            other: self.var_name(other, name),
+                        });
+                    }
+                }
+            } else {
+                for name in var.required_if.iter() {
+                    let other = self.parser.find_var(name);
+                    if self.set_vars.contains(&other) {
+                        return Err(ParseError::MissingRequirement {
+                            option: self.var_name(other, name),
+                            requirement: self.unset_var_name(var.id),
+                        });
                     }
                 }
             }
         }
-        return Parsed;
+        return Ok(());
+    }
+
+    fn check_exclusive_groups(&self) -> Result<(), ParseError> {
+        for group in self.parser.exclusive_groups.iter() {
+            let mut used = group.options.iter()
+                .filter_map(|id| self.used_options.get(id));
+            match (used.next(), used.next()) {
+                (Some(first), Some(second)) => {
+                    return Err(ParseError::ConflictingOptions {
+                        option: second.clone(),
+                        other: first.clone(),
+                    });
+                }
+                (None, _) if group.required => {
+                    let mut all_options = vec!();
+                    for &id in group.options.iter() {
+                        all_options.extend(self.parser.options[id].names
+                            .iter().map(|name| name.to_string()));
+                    }
+                    return Err(ParseError::MissingOption(all_options));
+                }
+                _ => {}
+            }
+        }
+        return Ok(());
+    }
+
+    /// Provenance of all variables of the parser
+    fn provenance(&mut self) -> Vec<VarOrigin> {
+        let mut result = Vec::new();
+        for var in self.parser.vars.iter() {
+            let mut names = Vec::new();
+            for opt in self.parser.options.iter() {
+                if opt.varid == Some(var.id) {
+                    names.extend(opt.names.iter().map(|x| x.to_string()));
+                }
+            }
+            for arg in self.parser.arguments.iter()
+                .chain(self.parser.catchall_argument.iter())
+            {
+                if arg.varid == var.id {
+                    names.push(arg.name.to_string());
+                }
+            }
+            for evar in self.parser.env_vars.iter() {
+                if evar.varid == var.id {
+                    names.push(evar.name.to_string());
+                }
+            }
+            for ckey in self.parser.config_keys.iter() {
+                if ckey.varid == var.id {
+                    names.push(ckey.key.to_string());
+                }
+            }
+            let mut origins = self.origins.remove(&var.id)
+                .unwrap_or_else(Vec::new);
+            let origin = origins.pop().unwrap_or_else(|| Origin {
+                source: Source::Default,
+                name: String::new(),
+                values: Vec::new(),
+            });
+            result.push(VarOrigin {
+                names: names,
+                origin: origin,
+                overridden: origins,
+            });
+        }
+        return result;
     }
 
-    fn parse(parser: &ArgumentParser, args: &Vec<String>, stderr: &mut Write)
-        -> ParseResult
+    fn parse<'p>(parser: &ArgumentParser<'p>, args: &Vec<OsString>,
+        stderr: &mut Write, path: &mut Vec<&'p str>,
+        provenance: &mut Vec<VarOrigin>)
+        -> Result<(), ParseError>
     {
+        parser.check_constraint_names();
+        let expanded;
+        let args = match parser.response_files {
+            ResponseFiles::Disabled => args,
+            mode => {
+                expanded = try!(response::expand(args, mode));
+                &expanded
+            }
+        };
         let mut ctx = Context {
             parser: parser,
             iter: args.iter().peekable(),
             set_vars: HashSet::new(),
+            config_vars: HashSet::new(),
+            used_options: HashMap::new(),
+            deprecation_warned: HashSet::new(),
+            origins: HashMap::new(),
             list_options: HashMap::new(),
             list_arguments: HashMap::new(),
             arguments: Vec::new(),
             stderr: stderr,
+            subcommand: None,
+            subcommand_args: Vec::new(),
         };
 
-        match ctx.parse_env_vars() {
-            Parsed => {}
-            x => { return x; }
-        }
-
-        match ctx.parse_options() {
-            Parsed => {}
-            x => { return x; }
-        }
-
-        match ctx.parse_arguments() {
-            Parsed => {}
-            x => { return x; }
-        }
-
-        match ctx.parse_list_vars() {
-            Parsed => {}
-            x => { return x; }
-        }
-
-        match ctx.check_required() {
-            Parsed => {}
-            x => { return x; }
+        try!(ctx.parse_env_vars());
+        try!(ctx.parse_config());
+        try!(ctx.parse_options());
+        try!(ctx.parse_arguments());
+        try!(ctx.parse_list_vars());
+        try!(ctx.check_required());
+        try!(ctx.check_exclusive_groups());
+        try!(ctx.check_constraints());
+        provenance.extend(ctx.provenance());
+
+        match ctx.subcommand {
+            Some(sub) => {
+                path.push(sub.name);
+                return Context::parse(&sub.parser, &ctx.subcommand_args,
+                    ctx.stderr, path, provenance);
+            }
+            None => {}
         }
 
-        return Parsed;
+        return Ok(());
     }
 }
 
@@ -530,16 +1127,45 @@ impl<'parser, 'refer, T> Ref<'parser, 'refer, T> {
                 }
             }
         }
+        self.set_completion(action.completion());
+        self.set_arity(action.arity());
         self.parser.add_option_for(Some(self.varid), names,
             action.bind(self.cell.clone()),
             help);
         return self;
     }
 
+    /// A key in the configuration to get option value from
+    ///
+    /// The value is used if the variable is not set by the command-line
+    /// or an environment variable. It's parsed by the `action` just like a
+    /// value of an option (e.g. `Store`, `Collect` or a `FromCommandLine`
+    /// type with `Parse`). List actions get the value split by the
+    /// `Ref::delimiter`. See `ArgumentParser::set_config`.
+    pub fn config_key<'x, A: TypedAction<T>>(&'x mut self,
+        key: &'parser str, action: A)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        let act = action.bind(self.cell.clone());
+        match act {
+            Flag(_) => panic!("Flag actions can't be used for config key {}",
+                key),
+            _ => {}
+        }
+        self.parser.config_keys.push(ConfigKey {
+            varid: self.varid,
+            key: key,
+            action: act,
+            });
+        return self;
+    }
+
     pub fn add_argument<'x, A: TypedAction<T>>(&'x mut self,
         name: &'parser str, action: A, help: &'parser str)
         -> &'x mut Ref<'parser, 'refer, T>
     {
+        self.set_completion(action.completion());
+        self.set_arity(action.arity());
         let act = action.bind(self.cell.clone());
         let opt = Rc::new(GenericArgument {
             id: self.parser.arguments.len(),
@@ -557,9 +1183,12 @@ impl<'parser, 'refer, T> Ref<'parser, 'refer, T> {
                         name, y.name)),
                     None => {},
                 }
+                if !self.parser.subcommands.is_empty() {
+                    panic!("Argument {} conflicts with commands", name);
+                }
                 self.parser.catchall_argument = Some(opt);
             }
-            Single(_) => {
+            Single(_) | Optional(..) => {
                 self.parser.arguments.push(opt);
             }
         }
@@ -572,6 +1201,40 @@ impl<'parser, 'refer, T> Ref<'parser, 'refer, T> {
         return self;
     }
 
+    /// Set what shell completion should offer for the value
+    ///
+    /// By default it's deduced from the action, e.g. `Parse` into a
+    /// `PathBuf` completes file names. Use `Completion::Values` to offer
+    /// a fixed list of values (useful for enums).
+    pub fn completion<'x>(&'x mut self, completion: Completion)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        {
+            let var = &mut self.parser.vars[self.varid];
+            var.completion = completion;
+        }
+        return self;
+    }
+
+    /// Set the default value shown in help
+    pub(crate) fn default_text(&mut self, value: String) {
+        self.parser.vars[self.varid].default = Some(value);
+    }
+
+    fn set_completion(&mut self, completion: Completion) {
+        let var = &mut self.parser.vars[self.varid];
+        if var.completion == Completion::Default {
+            var.completion = completion;
+        }
+    }
+
+    fn set_arity(&mut self, arity: Option<Arity>) {
+        let var = &mut self.parser.vars[self.varid];
+        if var.arity.is_none() {
+            var.arity = arity;
+        }
+    }
+
     pub fn metavar<'x>(&'x mut self, name: &str)
         -> &'x mut Ref<'parser, 'refer, T>
     {
@@ -582,43 +1245,250 @@ impl<'parser, 'refer, T> Ref<'parser, 'refer, T> {
         return self;
     }
 
-    pub fn required<'x>(&'x mut self)
+    /// Set the number of values of a list option or positional argument
+    ///
+    /// Accepts a number (`2`), an inclusive range (`1..=3`) or an open
+    /// range (`1..`). Applies to the `List` and `Collect` families of
+    /// actions: a list option takes values up to the maximum (or until the
+    /// next option) and it's an error to give fewer values than the
+    /// minimum. The metavar is repeated in help, e.g. `--point X X`, unless
+    /// it names every value itself, e.g. `.metavar("X Y")`.
+    pub fn nargs<'x, A: Into<Arity>>(&'x mut self, arity: A)
         -> &'x mut Ref<'parser, 'refer, T>
     {
         {
             let var = &mut self.parser.vars[self.varid];
-            var.required = true;
+            var.arity = Some(arity.into());
         }
         return self;
     }
-}
 
-impl<'parser, 'refer, T: 'static + FromStr> Ref<'parser, 'refer, T> {
-    pub fn envvar<'x>(&'x mut self, varname: &'parser str)
+    /// Split every value of a list option or positional argument
+    ///
+    /// Applies to the `List` and `Collect` families of actions, so
+    /// `--tags a,b --tags c` gives three values with the `','` delimiter.
+    /// A backslash escapes the delimiter (`a\,b`) or itself (`\\`). Values
+    /// of `Ref::envvar_list` are split too. The arity (see `Ref::nargs`)
+    /// counts the values after splitting, so `--point 1,2` gives two.
+    pub fn delimiter<'x>(&'x mut self, delimiter: char)
         -> &'x mut Ref<'parser, 'refer, T>
     {
-        self.parser.env_vars.push(Rc::new(EnvVar {
-            varid: self.varid,
-            name: varname,
-            action: Box::new(StoreAction { cell: self.cell.clone() }),
-            }));
+        {
+            let var = &mut self.parser.vars[self.varid];
+            var.delimiter = Some(delimiter);
+        }
         return self;
     }
-}
-
-/// The main argument parser class
-pub struct ArgumentParser<'parser> {
-    description: &'parser str,
-    vars: Vec<Box<Var>>,
-    options: Vec<Rc<GenericOption<'parser>>>,
-    arguments: Vec<Rc<GenericArgument<'parser>>>,
-    env_vars: Vec<Rc<EnvVar<'parser>>>,
-    catchall_argument: Option<Rc<GenericArgument<'parser>>>,
-    short_options: HashMap<char, Rc<GenericOption<'parser>>>,
-    long_options: HashMap<String, Rc<GenericOption<'parser>>>,
-    stop_on_first_argument: bool,
-    silence_double_dash: bool,
-}
+
+    pub fn required<'x>(&'x mut self)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        {
+            let var = &mut self.parser.vars[self.varid];
+            var.required = true;
+        }
+        return self;
+    }
+
+    /// If this variable is set, the option (or positional argument) `name`
+    /// must be set too
+    pub fn requires<'x>(&'x mut self, name: &str)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        {
+            let var = &mut self.parser.vars[self.varid];
+            var.requires.push(name.to_string());
+        }
+        return self;
+    }
+
+    /// The variable can't be set together with the option (or positional
+    /// argument) `name`
+    pub fn conflicts_with<'x>(&'x mut self, name: &str)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        {
+            let var = &mut self.parser.vars[self.varid];
+            var.conflicts_with.push(name.to_string());
+        }
+        return self;
+    }
+
+    /// The variable is required if the option (or positional argument)
+    /// `name` is set
+    pub fn required_if<'x>(&'x mut self, name: &str)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        {
+            let var = &mut self.parser.vars[self.varid];
+            var.required_if.push(name.to_string());
+        }
+        return self;
+    }
+}
+
+impl<'parser, 'refer> Ref<'parser, 'refer, bool> {
+    /// Add a flag which may be negated
+    ///
+    /// Each long name `--foo` also gets `--no-foo` which sets the variable
+    /// to `false`, while the names themselves set it to `true`. Shown in
+    /// help as a single `--[no-]foo` option. Environment variables and
+    /// configuration values of the variable accept `1/0`, `yes/no`,
+    /// `on/off` and `true/false`.
+    pub fn add_negatable_option<'x>(&'x mut self, names: &[&'parser str],
+        help: &'parser str)
+        -> &'x mut Ref<'parser, 'refer, bool>
+    {
+        self.parser.vars[self.varid].boolean = true;
+        self.parser.add_option_for(Some(self.varid), names,
+            Flag(Box::new(StoreConstAction {
+                cell: self.cell.clone(), value: true })),
+            help);
+        let positive = self.parser.options.last().unwrap().clone();
+        self.parser.negatable_options.insert(positive.id);
+        let negative = Rc::new(GenericOption {
+            id: positive.id,
+            varid: positive.varid,
+            names: positive.names.clone(),
+            help: help,
+            action: Flag(Box::new(StoreConstAction {
+                cell: self.cell.clone(), value: false })),
+            group: positive.group,
+        });
+        for name in names.iter() {
+            if name.starts_with("--") {
+                self.parser.long_options.insert(
+                    format!("--no-{}", &name[2..]), negative.clone());
+            }
+        }
+        return self;
+    }
+}
+
+impl<'parser, 'refer, T: Display> Ref<'parser, 'refer, T> {
+    /// Show the current value of the variable in help as `[default: X]`
+    ///
+    /// The value is formatted with `Display` when this method is called,
+    /// so call it after the variable is initialized
+    pub fn show_default<'x>(&'x mut self)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        let value = format!("{}", **self.cell.borrow());
+        self.default_text(value);
+        return self;
+    }
+}
+
+impl<'parser, 'refer, T: Debug> Ref<'parser, 'refer, T> {
+    /// Same as `show_default` but formats the value with `Debug`
+    pub fn show_default_debug<'x>(&'x mut self)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        let value = format!("{:?}", **self.cell.borrow());
+        self.default_text(value);
+        return self;
+    }
+}
+
+impl<'parser, 'refer, T> Ref<'parser, 'refer, Vec<T>>
+    where T: 'static + FromStr + Clone
+{
+    /// An environment variable to get the list from
+    ///
+    /// The value is split by the `Ref::delimiter` (if set), and every item
+    /// is parsed with `FromStr::from_str`, like the `Collect` action does.
+    pub fn envvar_list<'x>(&'x mut self, varname: &'parser str)
+        -> &'x mut Ref<'parser, 'refer, Vec<T>>
+    {
+        self.parser.env_vars.push(Rc::new(EnvVar {
+            varid: self.varid,
+            name: varname,
+            action: Collect.bind(self.cell.clone()),
+            }));
+        return self;
+    }
+}
+
+impl<'parser, 'refer, T: 'static + FromStr> Ref<'parser, 'refer, T> {
+    pub fn envvar<'x>(&'x mut self, varname: &'parser str)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        self.parser.env_vars.push(Rc::new(EnvVar {
+            varid: self.varid,
+            name: varname,
+            action: Single(Box::new(StoreAction {
+                cell: self.cell.clone() })),
+            }));
+        return self;
+    }
+
+    /// Add a subcommand handled by a separate parser
+    ///
+    /// When the command name is encountered after all the positional
+    /// arguments of this parser, the name is stored in the variable (parsed
+    /// with `FromStr::from_str`) and the rest of the command-line is parsed
+    /// by `parser`.
+    pub fn add_subcommand<'x>(&'x mut self, name: &'parser str,
+        help: &'parser str, parser: ArgumentParser<'parser>)
+        -> &'x mut Ref<'parser, 'refer, T>
+    {
+        match self.parser.catchall_argument {
+            Some(ref y) => panic!("Command {} conflicts with argument {}",
+                name, y.name),
+            None => {}
+        }
+        self.parser.subcommands.push(Rc::new(Subcommand {
+            varid: self.varid,
+            name: name,
+            help: help,
+            parser: parser,
+            action: Box::new(StoreAction { cell: self.cell.clone() }),
+            }));
+        {
+            let var = &mut self.parser.vars[self.varid];
+            if var.metavar.is_empty() {
+                var.metavar = "command".to_string();
+            }
+        }
+        return self;
+    }
+}
+
+/// The main argument parser class
+pub struct ArgumentParser<'parser> {
+    pub(crate) description: &'parser str,
+    pub(crate) epilog: &'parser str,
+    pub(crate) sections: Vec<HelpSection<'parser>>,
+    pub(crate) examples: Vec<Example<'parser>>,
+    pub(crate) vars: Vec<Box<Var>>,
+    pub(crate) options: Vec<Rc<GenericOption<'parser>>>,
+    pub(crate) arguments: Vec<Rc<GenericArgument<'parser>>>,
+    pub(crate) env_vars: Vec<Rc<EnvVar<'parser>>>,
+    pub(crate) config_keys: Vec<ConfigKey<'parser>>,
+    pub(crate) config: Option<&'parser Config>,
+    pub(crate) catchall_argument: Option<Rc<GenericArgument<'parser>>>,
+    pub(crate) subcommands: Vec<Rc<Subcommand<'parser>>>,
+    pub(crate) exclusive_groups: Vec<ExclusiveGroup>,
+    pub(crate) help_groups: Vec<HelpGroup<'parser>>,
+    pub(crate) short_options: HashMap<char, Rc<GenericOption<'parser>>>,
+    pub(crate) long_options: HashMap<String, Rc<GenericOption<'parser>>>,
+    pub(crate) stop_on_first_argument: bool,
+    pub(crate) silence_double_dash: bool,
+    pub(crate) response_files: ResponseFiles,
+    pub(crate) hidden_options: HashSet<String>,
+    /// Ids of the options having `--no-` counterparts
+    pub(crate) negatable_options: HashSet<usize>,
+    pub(crate) deprecated_options: HashMap<String, String>,
+    pub(crate) color: ColorChoice,
+    /// The choice of the `--color` option, reset after each parse
+    pub(crate) color_option: Rc<Cell<Option<ColorChoice>>>,
+    pub(crate) theme: Theme,
+    pub(crate) width: Option<usize>,
+    pub(crate) option_width: Option<usize>,
+    pub(crate) show_defaults: bool,
+    pub(crate) show_env_vars: bool,
+    pub(crate) show_possible_values: bool,
+}
 
 
 
@@ -629,15 +1499,35 @@ impl<'parser> ArgumentParser<'parser> {
 
         let mut ap = ArgumentParser {
             description: "",
+            epilog: "",
+            sections: Vec::new(),
+            examples: Vec::new(),
             vars: Vec::new(),
             env_vars: Vec::new(),
+            config_keys: Vec::new(),
+            config: None,
             arguments: Vec::new(),
             catchall_argument: None,
+            subcommands: Vec::new(),
+            exclusive_groups: Vec::new(),
+            help_groups: Vec::new(),
             options: Vec::new(),
             short_options: HashMap::new(),
             long_options: HashMap::new(),
             stop_on_first_argument: false,
             silence_double_dash: true,
+            response_files: ResponseFiles::Disabled,
+            hidden_options: HashSet::new(),
+            negatable_options: HashSet::new(),
+            deprecated_options: HashMap::new(),
+            color: ColorChoice::Auto,
+            color_option: Rc::new(Cell::new(None)),
+            theme: Theme::default(),
+            width: None,
+            option_width: None,
+            show_defaults: true,
+            show_env_vars: true,
+            show_possible_values: true,
             };
         ap.add_option_for(None, &["-h", "--help"], Flag(Box::new(HelpAction)),
             "Show this help message and exit");
@@ -656,6 +1546,14 @@ impl<'parser> ArgumentParser<'parser> {
                 id: id,
                 required: false,
                 metavar: "".to_string(),
+                completion: Completion::Default,
+                requires: Vec::new(),
+                conflicts_with: Vec::new(),
+                required_if: Vec::new(),
+                default: None,
+                boolean: false,
+                arity: None,
+                delimiter: None,
                 }));
         return Box::new(Ref {
             cell: cell.clone(),
@@ -679,6 +1577,268 @@ impl<'parser> ArgumentParser<'parser> {
         self.description = descr;
     }
 
+    /// Set text shown at the end of help
+    ///
+    /// The text is wrapped, blank lines separate paragraphs
+    pub fn set_epilog(&mut self, epilog: &'parser str) {
+        self.epilog = epilog;
+    }
+
+    /// Add an example to the "Examples" section of help
+    ///
+    /// The `command` is shown as is, below the `description` (which is
+    /// wrapped and may be empty)
+    pub fn add_example(&mut self, description: &'parser str,
+        command: &'parser str)
+    {
+        self.examples.push(Example {
+            description: description,
+            command: command,
+        });
+    }
+
+    /// Add a section with a title to help, shown after the options
+    ///
+    /// The text is wrapped, blank lines separate paragraphs. Sections are
+    /// shown in the order they were added.
+    pub fn add_section(&mut self, title: &'parser str, text: &'parser str) {
+        self.sections.push(HelpSection {
+            title: title,
+            text: text,
+            verbatim: false,
+        });
+    }
+
+    /// Add a section with preformatted text to help
+    ///
+    /// Works like `add_section`, but the text is not wrapped. Common
+    /// indentation and leading and trailing blank lines are removed, so
+    /// the text may be indented to match the code.
+    pub fn add_verbatim_section(&mut self, title: &'parser str,
+        text: &'parser str)
+    {
+        self.sections.push(HelpSection {
+            title: title,
+            text: text,
+            verbatim: true,
+        });
+    }
+
+    /// Start a new section of options in help
+    ///
+    /// All the options added after this call are shown in help under the
+    /// `title` (and the `description` if not empty) instead of "Optional
+    /// arguments", so the common options should be added first. Sections
+    /// are shown in the order they were added.
+    pub fn add_group(&mut self, title: &'parser str,
+        description: &'parser str)
+    {
+        self.help_groups.push(HelpGroup {
+            title: title,
+            description: description,
+        });
+    }
+
+    /// Declare that options can't be used together
+    ///
+    /// Any name of an already added option may be used to refer to it.
+    /// If `required` is true, exactly one of the options must be specified.
+    /// Shown in usage as `[--json | --yaml]` (or `(--json | --yaml)` if
+    /// required).
+    pub fn add_exclusive_group(&mut self, names: &[&str], required: bool) {
+        let mut options = Vec::new();
+        for name in names.iter() {
+            match self.find_option(name) {
+                Some(opt) => options.push(opt.id),
+                None => panic!("Unknown option {} in exclusive group", name),
+            }
+        }
+        self.exclusive_groups.push(ExclusiveGroup {
+            options: options,
+            required: required,
+        });
+    }
+
+    /// Hide the option name from help, usage, man page and completion
+    ///
+    /// The option still works. Each name (alias) is hidden separately,
+    /// the option is not shown at all when all its names are hidden.
+    pub fn hide_option(&mut self, name: &str) {
+        if self.find_option(name).is_none() {
+            panic!("Unknown option {}", name);
+        }
+        self.hidden_options.insert(name.to_string());
+    }
+
+    /// Mark the option name as deprecated
+    ///
+    /// The option still works, but using it writes a warning to the
+    /// stderr, e.g. `WARNING: Option --old is deprecated: use --new`
+    /// (the `message` is omitted if empty). Other names (aliases) of the
+    /// same option are not affected.
+    pub fn deprecate_option(&mut self, name: &str, message: &str) {
+        if self.find_option(name).is_none() {
+            panic!("Unknown option {}", name);
+        }
+        self.deprecated_options.insert(name.to_string(), message.to_string());
+    }
+
+    pub(crate) fn is_hidden(&self, name: &str) -> bool {
+        return self.hidden_options.contains(name);
+    }
+
+    /// Names of the option which are not hidden
+    pub(crate) fn visible_names<'x>(&self, opt: &'x GenericOption)
+        -> Vec<&'x str>
+    {
+        return opt.names.iter().map(|x| *x)
+            .filter(|x| !self.is_hidden(x)).collect();
+    }
+
+    /// Names of the option which are not hidden, including `--no-foo`
+    /// for every long name of a negatable option
+    pub(crate) fn completion_names(&self, opt: &GenericOption) -> Vec<String>
+    {
+        let mut names = Vec::new();
+        for name in self.visible_names(opt) {
+            names.push(name.to_string());
+            if name.starts_with("--")
+                && self.negatable_options.contains(&opt.id)
+            {
+                names.push(format!("--no-{}", &name[2..]));
+            }
+        }
+        return names;
+    }
+
+    /// Visible long option names in the order of declaration
+    ///
+    /// Includes `--no-foo` of negatable options. Used for suggestions, so
+    /// the order must be deterministic.
+    pub(crate) fn long_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for opt in self.options.iter() {
+            names.extend(self.completion_names(opt).into_iter()
+                .filter(|name| name.starts_with("--")));
+        }
+        return names;
+    }
+
+    /// Whether the positional argument accepts `num` values
+    ///
+    /// The arity of a collection (like `Vec<(u32, u32)>`) is the number of
+    /// values in each item, so the values are checked in groups.
+    pub(crate) fn accepts_values(&self, arg: &GenericArgument, num: usize)
+        -> bool
+    {
+        match (self.vars[arg.varid].arity, &arg.action) {
+            (Some(arity), &Push(_)) => return arity.contains_groups(num),
+            (Some(arity), _) => return arity.contains(num),
+            (None, _) => return true,
+        }
+    }
+
+    /// Values split by the delimiter of the variable, if any
+    pub(crate) fn split_value(&self, varid: usize, value: &OsStr)
+        -> Vec<OsString>
+    {
+        match self.vars[varid].delimiter {
+            Some(delimiter) => return split_list(value, delimiter),
+            None => return vec![value.to_os_string()],
+        }
+    }
+
+    pub(crate) fn split_values(&self, varid: usize, values: &[&OsStr])
+        -> Vec<OsString>
+    {
+        return values.iter()
+            .flat_map(|value| self.split_value(varid, value)).collect();
+    }
+
+    /// Metavar of the option values as shown in help
+    ///
+    /// Repeated according to the arity of list options, e.g. `X X`
+    pub(crate) fn value_label(&self, opt: &GenericOption) -> String {
+        let var = &self.vars[opt.varid.unwrap()];
+        match (&opt.action, var.arity) {
+            (&Push(_), Some(arity)) | (&Many(_), Some(arity))
+                if !var.metavar.contains(' ')
+            => {
+                return repeat_metavar(&var.metavar, arity);
+            }
+            _ => return var.metavar.clone(),
+        }
+    }
+
+    /// Name of the option as shown in help: `--[no-]foo` for negatable
+    /// options
+    pub(crate) fn display_name(&self, opt: &GenericOption, name: &str)
+        -> String
+    {
+        if self.negatable_options.contains(&opt.id) && name.starts_with("--")
+        {
+            return format!("--[no-]{}", &name[2..]);
+        }
+        return name.to_string();
+    }
+
+    /// Whether the option has any name which is not hidden
+    pub(crate) fn is_visible(&self, opt: &GenericOption) -> bool {
+        return opt.names.iter().any(|x| !self.is_hidden(x));
+    }
+
+    /// Whether there are visible options besides `--help`, so that
+    /// `[OPTIONS]` is shown in usage
+    pub(crate) fn has_options(&self) -> bool {
+        let short = self.short_options.keys()
+            .filter(|c| !self.is_hidden(&format!("-{}", c))).count();
+        let long = self.long_options.keys()
+            .filter(|x| !self.is_hidden(x)).count();
+        return short > 1 || long > 1;
+    }
+
+    fn find_option(&self, name: &str) -> Option<&Rc<GenericOption<'parser>>> {
+        match ArgumentKind::check(name) {
+            LongOption => return self.long_options.get(name),
+            ShortOption if name.len() == 2 => {
+                return self.short_options.get(&(name.as_bytes()[1] as char));
+            }
+            _ => return None,
+        }
+    }
+
+    /// Panics if any `requires`, `conflicts_with` or `required_if` name is
+    /// unknown
+    ///
+    /// Checked before parsing, as names may refer to options declared
+    /// later, so the panic doesn't depend on the command-line
+    fn check_constraint_names(&self) {
+        for var in self.vars.iter() {
+            for name in var.requires.iter()
+                .chain(var.conflicts_with.iter())
+                .chain(var.required_if.iter())
+            {
+                self.find_var(name);
+            }
+        }
+    }
+
+    /// Find the variable by an option name or positional argument name
+    fn find_var(&self, name: &str) -> usize {
+        match self.find_option(name).and_then(|opt| opt.varid) {
+            Some(varid) => return varid,
+            None => {}
+        }
+        for arg in self.arguments.iter()
+            .chain(self.catchall_argument.iter())
+        {
+            if arg.name == name {
+                return arg.varid;
+            }
+        }
+        panic!("Unknown option or argument {}", name);
+    }
+
     fn add_option_for(&mut self, var: Option<usize>,
         names: &[&'parser str],
         action: Action<'parser>, help: &'parser str)
@@ -689,6 +1849,7 @@ impl<'parser> ArgumentParser<'parser> {
             names: names.to_vec(),
             help: help,
             action: action,
+            group: self.help_groups.len().checked_sub(1),
             });
 
         if names.is_empty() {
@@ -721,7 +1882,7 @@ impl<'parser> ArgumentParser<'parser> {
     /// Usually command-line option is used for printing help,
     /// this is here for any awkward cases
     pub fn print_help(&self, name: &str, writer: &mut Write) -> IoResult<()> {
-        return HelpFormatter::print_help(self, name, writer);
+        return HelpFormatter::print_help(self, name, writer, None, None);
     }
 
     /// Print usage
@@ -729,7 +1890,32 @@ impl<'parser> ArgumentParser<'parser> {
     /// Usually printed into stderr on error of command-line parsing
     pub fn print_usage(&self, name: &str, writer: &mut Write) -> IoResult<()>
     {
-        return HelpFormatter::print_usage(self, name, writer);
+        return HelpFormatter::print_usage(self, name, writer, None, None);
+    }
+
+    /// Write man page
+    ///
+    /// Renders the same information as `--help` (and environment
+    /// variables) in roff format. The `section` is the manual section,
+    /// usually `"1"` for user commands.
+    pub fn print_man_page(&self, name: &str, section: &str,
+        writer: &mut Write)
+        -> IoResult<()>
+    {
+        return ManFormatter::print_man_page(self, name, section, writer);
+    }
+
+    /// Write shell completion script
+    ///
+    /// The `name` is the name of the executable as typed in the shell.
+    /// The script is usually written at build or install time into
+    /// a location appropriate for the shell.
+    pub fn print_completion(&self, shell: Shell, name: &str,
+        writer: &mut Write)
+        -> IoResult<()>
+    {
+        return CompletionFormatter::print_completion(self, shell, name,
+            writer);
     }
 
     /// Parse arguments
@@ -740,28 +1926,190 @@ impl<'parser> ArgumentParser<'parser> {
         stdout: &mut Write, stderr: &mut Write)
         -> Result<(), i32>
     {
-        let name = if !args.is_empty() { &args[0][..] } else { "unknown" };
-        match Context::parse(self, &args, stderr) {
-            Parsed => return Ok(()),
-            Exit => return Err(0),
-            Help => {
-                self.print_help(name, stdout).unwrap();
+        return self.parse_os(args.into_iter().map(OsString::from).collect(),
+            stdout, stderr);
+    }
+
+    /// Parse arguments which are not necessarily valid UTF-8
+    ///
+    /// Works like `parse`. The `Parse` family of actions into `PathBuf` or
+    /// `OsString` receive the raw value, other actions report an error
+    /// on invalid UTF-8.
+    pub fn parse_os(&self, args: Vec<OsString>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<(), i32>
+    {
+        return self.parse_styled(args, stdout, stderr, false, false, None);
+    }
+
+    /// Parse arguments and write help and errors into the standard
+    /// streams, colored if they are terminals
+    pub(crate) fn parse_terminal(&self, args: Vec<OsString>)
+        -> Result<(), i32>
+    {
+        return self.parse_styled(args, &mut stdout(), &mut stderr(),
+            stdout().is_terminal(), stderr().is_terminal(),
+            terminal::width());
+    }
+
+    fn parse_styled(&self, args: Vec<OsString>,
+        stdout: &mut Write, stderr: &mut Write,
+        stdout_terminal: bool, stderr_terminal: bool,
+        terminal_width: Option<usize>)
+        -> Result<(), i32>
+    {
+        let (parser, name, res) = self.parse_command(&args, stderr,
+            &mut Vec::new());
+        let color = self.take_color();
+        match res {
+            Ok(()) => return Ok(()),
+            Err(ParseError::Help) => {
+                HelpFormatter::print_help(parser, &name[..], stdout,
+                    self.styled_theme(color, stdout_terminal), terminal_width)
+                    .unwrap();
                 return Err(0);
             }
-            Error(message) => {
-                self.error(&name[..], &message[..], stderr);
-                return Err(2);
+            Err(ParseError::Exit) => return Err(0),
+            Err(err) => {
+                parser.write_error(&name[..], &err.to_string()[..], stderr,
+                    self.styled_theme(color, stderr_terminal), terminal_width);
+                return Err(err.exit_code());
+            }
+        }
+    }
+
+    /// The choice of the `--color` option (or of `set_color`)
+    ///
+    /// Resets the option, so it doesn't affect the next parse
+    fn take_color(&self) -> ColorChoice {
+        return self.color_option.take().unwrap_or(self.color);
+    }
+
+    /// The theme if colors are enabled for the output
+    fn styled_theme(&self, color: ColorChoice, terminal: bool)
+        -> Option<&Theme>
+    {
+        if color.enabled(terminal) {
+            return Some(&self.theme);
+        }
+        return None;
+    }
+
+    /// Parse arguments and return a structured error
+    ///
+    /// Works like `parse` but instead of writing an error message and
+    /// returning the exit code it returns the `ParseError` so that the
+    /// caller can react on it. The help is still printed into `stdout` on
+    /// `--help`, which is reported as `ParseError::Help`.
+    pub fn try_parse(&self, args: Vec<String>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<(), ParseError>
+    {
+        return self.try_parse_os(
+            args.into_iter().map(OsString::from).collect(), stdout, stderr);
+    }
+
+    /// Parse arguments which are not necessarily valid UTF-8 and return a
+    /// structured error
+    ///
+    /// See `parse_os` and `try_parse`
+    pub fn try_parse_os(&self, args: Vec<OsString>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<(), ParseError>
+    {
+        let (parser, name, res) = self.parse_command(&args, stderr,
+            &mut Vec::new());
+        let color = self.take_color();
+        match res {
+            Err(ParseError::Help) => {
+                HelpFormatter::print_help(parser, &name[..], stdout,
+                    self.styled_theme(color, false), None).unwrap();
+            }
+            _ => {}
+        }
+        return res;
+    }
+
+    /// Parse arguments and report where each value came from
+    ///
+    /// Works like `try_parse` but on success returns the `Provenance`
+    /// report: for every variable whether it was set on the command-line,
+    /// from environment variable, from configuration or kept its default,
+    /// along with the name and the raw values.
+    pub fn try_parse_provenance(&self, args: Vec<String>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<Provenance, ParseError>
+    {
+        return self.try_parse_os_provenance(
+            args.into_iter().map(OsString::from).collect(), stdout, stderr);
+    }
+
+    /// Same as `try_parse_provenance` for arguments which are not
+    /// necessarily valid UTF-8
+    pub fn try_parse_os_provenance(&self, args: Vec<OsString>,
+        stdout: &mut Write, stderr: &mut Write)
+        -> Result<Provenance, ParseError>
+    {
+        let mut vars = Vec::new();
+        let (parser, name, res) = self.parse_command(&args, stderr,
+            &mut vars);
+        let color = self.take_color();
+        match res {
+            Ok(()) => return Ok(Provenance { vars: vars }),
+            Err(ParseError::Help) => {
+                HelpFormatter::print_help(parser, &name[..], stdout,
+                    self.styled_theme(color, false), None).unwrap();
+                return Err(ParseError::Help);
             }
+            Err(err) => return Err(err),
         }
     }
 
+    /// Returns (sub)parser where parsing has stopped and its full name
+    fn parse_command<'x>(&'x self, args: &Vec<OsString>,
+        stderr: &mut Write, provenance: &mut Vec<VarOrigin>)
+        -> (&'x ArgumentParser<'parser>, String, Result<(), ParseError>)
+    {
+        let mut path = Vec::new();
+        let res = Context::parse(self, args, stderr, &mut path, provenance);
+        let mut name = if !args.is_empty() {
+            args[0].to_string_lossy().into_owned()
+        } else {
+            "unknown".to_string()
+        };
+        let mut parser = self;
+        for cmd in path.iter() {
+            parser = &parser.subcommands.iter()
+                .find(|sub| sub.name == *cmd).unwrap().parser;
+            name.push(' ');
+            name.push_str(cmd);
+        }
+        return (parser, name, res);
+    }
+
     /// Write an error similar to one produced by the library itself
     ///
     /// Only needed if you like to do some argument validation that is out
     /// of scope of the argparse
     pub fn error(&self, command: &str, message: &str, writer: &mut Write) {
-        self.print_usage(command, writer).unwrap();
-        writeln!(writer, "{}: {}", command, message).ok();
+        self.write_error(command, message, writer, None, None);
+    }
+
+    fn write_error(&self, command: &str, message: &str, writer: &mut Write,
+        theme: Option<&Theme>, terminal_width: Option<usize>)
+    {
+        HelpFormatter::print_usage(self, command, writer, theme,
+            terminal_width).unwrap();
+        match theme {
+            Some(theme) => {
+                writeln!(writer, "{} {}",
+                    Theme::paint(&theme.error, &format!("{}:", command)),
+                    message).ok();
+            }
+            None => {
+                writeln!(writer, "{}: {}", command, message).ok();
+            }
+        }
     }
 
     /// Configure parser to ignore options when first non-option argument is
@@ -789,14 +2137,116 @@ impl<'parser> ArgumentParser<'parser> {
         self.silence_double_dash = silence;
     }
 
+    /// Set configuration to get values for `Ref::config_key` from
+    ///
+    /// The precedence is: command-line, environment variables, the
+    /// configuration and the default value
+    pub fn set_config(&mut self, config: &'parser Config) {
+        self.config = Some(config);
+    }
+
+    /// Expand `@path` arguments into arguments read from the file
+    ///
+    /// Disabled by default. See `ResponseFiles` for the supported formats.
+    pub fn response_files(&mut self, mode: ResponseFiles) {
+        self.response_files = mode;
+    }
+
+    /// When to use colors in help, usage and error messages
+    ///
+    /// Colors are only used by `parse_args` and `parse_args_or_exit`
+    /// (which know whether the output is a terminal) and by other parse
+    /// methods with `ColorChoice::Always`. The default is
+    /// `ColorChoice::Auto`, which disables colors if the `NO_COLOR`
+    /// environment variable is set. The setting of the top-level parser
+    /// is used for subcommands too.
+    pub fn set_color(&mut self, choice: ColorChoice) {
+        self.color = choice;
+    }
+
+    /// Set the total width of the help output
+    ///
+    /// By default `parse_args` and `parse_args_or_exit` use the width of
+    /// the terminal (or the `COLUMNS` environment variable) and other
+    /// methods use 79 characters.
+    pub fn set_width(&mut self, width: usize) {
+        self.width = Some(width);
+    }
+
+    /// Set the width of the column of option names in help
+    ///
+    /// By default the column is wide enough for the longest option with
+    /// its metavar, but at least 24 characters and at most a third of the
+    /// total width. Longer options have their help on the next line.
+    pub fn set_option_width(&mut self, width: usize) {
+        self.option_width = Some(width);
+    }
+
+    /// Show `[default: X]` in help for variables having
+    /// `Ref::show_default`
+    ///
+    /// On by default, but the default is only shown for variables which
+    /// call `Ref::show_default` (or `Ref::show_default_debug`). Variables
+    /// of any type may be registered, and only the ones implementing
+    /// `Display` (or `Debug`) can be formatted, so the value is captured
+    /// per variable rather than by this switch.
+    pub fn show_defaults(&mut self, show: bool) {
+        self.show_defaults = show;
+    }
+
+    /// Show `[env: NAME]` in help for variables having `Ref::envvar`
+    ///
+    /// On by default
+    pub fn show_env_vars(&mut self, show: bool) {
+        self.show_env_vars = show;
+    }
+
+    /// Show `[possible values: a, b]` in help for variables completed
+    /// with `Completion::Values`
+    ///
+    /// On by default
+    pub fn show_possible_values(&mut self, show: bool) {
+        self.show_possible_values = show;
+    }
+
+    /// Set styles used when colors are enabled, see `set_color`
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Add `--color WHEN` option to choose `auto`, `always` or `never`
+    ///
+    /// The option overrides `set_color`. It applies to the help only if
+    /// specified before `--help`.
+    pub fn add_color_option(&mut self) {
+        let id = self.vars.len();
+        self.vars.push(Box::new(Var {
+                id: id,
+                required: false,
+                metavar: "WHEN".to_string(),
+                completion: Completion::Values(
+                    vec!["auto".to_string(), "always".to_string(),
+                         "never".to_string()]),
+                requires: Vec::new(),
+                conflicts_with: Vec::new(),
+                required_if: Vec::new(),
+                default: None,
+                boolean: false,
+                arity: None,
+                delimiter: None,
+                }));
+        let action = ColorAction { cell: self.color_option.clone() };
+        self.add_option_for(Some(id), &["--color"], Single(Box::new(action)),
+            "When to use colors: auto, always or never");
+    }
+
     /// Convenience method to parse arguments
     ///
     /// On error returns error code that is supposed to be returned by
     /// an application. (i.e. zero on `--help` and `2` on argument error)
     pub fn parse_args(&self) -> Result<(), i32> {
         // TODO(tailhook) can we get rid of collect?
-        return self.parse(env::args().collect(),
-            &mut stdout(), &mut stderr());
+        return self.parse_terminal(env::args_os().collect());
     }
 
     /// The simplest conveninece method
@@ -805,7 +2255,7 @@ impl<'parser> ArgumentParser<'parser> {
     /// appropriate code (including successful on `--help`) otherwise.
     pub fn parse_args_or_exit(&self) {
         // TODO(tailhook) can we get rid of collect?
-        self.parse(env::args().collect(), &mut stdout(), &mut stderr())
+        self.parse_terminal(env::args_os().collect())
             .map_err(|c| exit(c))
             .ok();
     }
@@ -815,77 +2265,224 @@ struct HelpFormatter<'a, 'b: 'a> {
     name: &'a str,
     parser: &'a ArgumentParser<'b>,
     buf: &'a mut (Write + 'a),
+    theme: Option<&'a Theme>,
+    width: usize,
+    option_width: usize,
+}
+
+/// The metavar repeated for every value: `X X`, `X [X]` or `X [X...]`
+pub(crate) fn repeat_metavar(metavar: &str, arity: Arity) -> String {
+    let mut parts = vec![metavar.to_string(); arity.min];
+    if arity.max == arity.min + 1 {
+        parts.push(format!("[{}]", metavar));
+    } else if arity.max > arity.min {
+        parts.push(format!("[{}...]", metavar));
+    }
+    return parts.join(" ");
+}
+
+/// Width of the option names with metavar as shown in help
+fn option_label_width(parser: &ArgumentParser, opt: &GenericOption) -> usize {
+    let names = parser.visible_names(opt);
+    if names.is_empty() {
+        return 0;
+    }
+    let mut num = names.iter()
+        .map(|x| parser.display_name(opt, x).len()).sum::<usize>()
+        + names.len() - 1;
+    match opt.action {
+        Flag(_) => {}
+        Single(_) | Push(_) | Many(_) => {
+            num += parser.value_label(opt).len() + 1;
+        }
+        Optional(..) => {
+            num += parser.vars[opt.varid.unwrap()].metavar.len() + 3;
+        }
+    }
+    return num;
 }
 
 impl<'a, 'b> HelpFormatter<'a, 'b> {
-    pub fn print_usage(parser: &ArgumentParser, name: &str, writer: &mut Write)
+    fn new(parser: &'a ArgumentParser<'b>, name: &'a str,
+        buf: &'a mut (Write + 'a), theme: Option<&'a Theme>,
+        terminal_width: Option<usize>)
+        -> HelpFormatter<'a, 'b>
+    {
+        let width = parser.width.or(terminal_width).unwrap_or(TOTAL_WIDTH);
+        let option_width = match parser.option_width {
+            Some(option_width) => option_width,
+            None => {
+                // names that don't fit anyway are put on a separate line
+                let limit = max(OPTION_WIDTH, width / 3);
+                let longest = parser.options.iter()
+                    .map(|opt| option_label_width(parser, opt))
+                    .chain(parser.arguments.iter()
+                        .chain(parser.catchall_argument.iter())
+                        .map(|arg| arg.name.len()))
+                    .chain(parser.subcommands.iter()
+                        .map(|sub| sub.name.len()))
+                    .filter(|&len| len + 4 <= limit)
+                    .max().unwrap_or(0);
+                max(longest + 4, OPTION_WIDTH)
+            }
+        };
+        return HelpFormatter {
+            parser: parser,
+            name: name,
+            buf: buf,
+            theme: theme,
+            width: width,
+            option_width: option_width,
+        };
+    }
+
+    pub fn print_usage(parser: &ArgumentParser, name: &str, writer: &mut Write,
+        theme: Option<&Theme>, terminal_width: Option<usize>)
         -> IoResult<()>
     {
-        return HelpFormatter { parser: parser, name: name, buf: writer }
+        return HelpFormatter::new(parser, name, writer, theme, terminal_width)
             .write_usage();
     }
 
-    pub fn print_help(parser: &ArgumentParser, name: &str, writer: &mut Write)
+    pub fn print_help(parser: &ArgumentParser, name: &str, writer: &mut Write,
+        theme: Option<&Theme>, terminal_width: Option<usize>)
         -> IoResult<()>
     {
-        return HelpFormatter { parser: parser, name: name, buf: writer }
+        return HelpFormatter::new(parser, name, writer, theme, terminal_width)
             .write_help();
     }
 
+    /// Write text in the style of the theme (if colors are enabled)
+    fn write_styled(&mut self, style: fn(&Theme) -> &String, text: &str)
+        -> IoResult<()>
+    {
+        match self.theme {
+            Some(theme) => {
+                let text = Theme::paint(style(theme), text);
+                return write!(self.buf, "{}", text);
+            }
+            None => return write!(self.buf, "{}", text),
+        }
+    }
+
     pub fn print_argument(&mut self, arg: &GenericArgument<'b>)
         -> IoResult<()>
     {
+        let help = self.annotated_help(Some(arg.varid), arg.help);
+        return self.print_entry(arg.name, &help);
+    }
+
+    /// Help text with the default value, environment variables and
+    /// possible values appended (if enabled)
+    fn annotated_help(&self, varid: Option<usize>, help: &str) -> String {
+        let parser = self.parser;
+        let var = match varid {
+            Some(varid) => &parser.vars[varid],
+            None => return help.to_string(),
+        };
+        let mut notes = Vec::new();
+        match var.default {
+            Some(ref value) if parser.show_defaults => {
+                notes.push(format!("[default: {}]", value));
+            }
+            _ => {}
+        }
+        if parser.show_env_vars {
+            let names: Vec<_> = parser.env_vars.iter()
+                .filter(|evar| evar.varid == var.id)
+                .map(|evar| evar.name)
+                .collect();
+            if !names.is_empty() {
+                notes.push(format!("[env: {}]", names.join(", ")));
+            }
+        }
+        match var.completion {
+            Completion::Values(ref values) if parser.show_possible_values
+            => {
+                notes.push(format!("[possible values: {}]",
+                    values.join(", ")));
+            }
+            _ => {}
+        }
+        if notes.is_empty() {
+            return help.to_string();
+        }
+        let notes = notes.join(" ");
+        if help.is_empty() {
+            return notes;
+        }
+        return format!("{} {}", help, notes);
+    }
+
+    pub fn print_subcommand(&mut self, sub: &Subcommand<'b>)
+        -> IoResult<()>
+    {
+        return self.print_entry(sub.name, sub.help);
+    }
+
+    fn print_entry(&mut self, name: &str, help: &str) -> IoResult<()> {
         let mut num = 2;
-        try!(write!(self.buf, "  {}", arg.name));
-        num += arg.name.len();
-        if num >= OPTION_WIDTH {
+        try!(write!(self.buf, "  "));
+        try!(self.write_styled(|t| &t.option, name));
+        num += name.len();
+        try!(self.write_help_text(num, help));
+        return Ok(());
+    }
+
+    /// Pad to the help column (or start a new line) and write wrapped help
+    fn write_help_text(&mut self, num: usize, help: &str) -> IoResult<()> {
+        if num >= self.option_width {
             try!(write!(self.buf, "\n"));
-            for _ in 0..OPTION_WIDTH {
+            for _ in 0..self.option_width {
                 try!(write!(self.buf, " "));
             }
         } else {
-            for _ in num..OPTION_WIDTH {
+            for _ in num..self.option_width {
                 try!(write!(self.buf, " "));
             }
         }
-        try!(wrap_text(self.buf, arg.help, TOTAL_WIDTH, OPTION_WIDTH));
+        try!(wrap_text(self.buf, help, self.width, self.option_width));
         try!(write!(self.buf, "\n"));
         return Ok(());
     }
 
     pub fn print_option(&mut self, opt: &GenericOption<'b>) -> IoResult<()> {
+        let names = self.parser.visible_names(opt);
+        if names.is_empty() {
+            return Ok(());
+        }
         let mut num = 2;
         try!(write!(self.buf, "  "));
-        let mut niter = opt.names.iter();
+        let names: Vec<_> = names.iter()
+            .map(|name| self.parser.display_name(opt, name)).collect();
+        let mut niter = names.iter();
         let name = niter.next().unwrap();
-        try!(write!(self.buf, "{}", name));
+        try!(self.write_styled(|t| &t.option, name));
         num += name.len();
         for name in niter {
             try!(write!(self.buf, ","));
-            try!(write!(self.buf, "{}", name));
+            try!(self.write_styled(|t| &t.option, name));
             num += name.len() + 1;
         }
         match opt.action {
             Flag(_) => {}
             Single(_) | Push(_) | Many(_) => {
                 try!(write!(self.buf, " "));
-                let var = &self.parser.vars[opt.varid.unwrap()];
-                try!(write!(self.buf, "{}", &var.metavar[..]));
-                num += var.metavar.len() + 1;
-            }
-        }
-        if num >= OPTION_WIDTH {
-            try!(write!(self.buf, "\n"));
-            for _ in 0..OPTION_WIDTH {
-                try!(write!(self.buf, " "));
+                let label = self.parser.value_label(opt);
+                try!(self.write_styled(|t| &t.metavar, &label));
+                num += label.len() + 1;
             }
-        } else {
-            for _ in num..OPTION_WIDTH {
-                try!(write!(self.buf, " "));
+            Optional(..) => {
+                try!(write!(self.buf, " ["));
+                let parser = self.parser;
+                let var = &parser.vars[opt.varid.unwrap()];
+                try!(self.write_styled(|t| &t.metavar, &var.metavar));
+                try!(write!(self.buf, "]"));
+                num += var.metavar.len() + 3;
             }
         }
-        try!(wrap_text(self.buf, opt.help, TOTAL_WIDTH, OPTION_WIDTH));
-        try!(write!(self.buf, "\n"));
+        let help = self.annotated_help(opt.varid, opt.help);
+        try!(self.write_help_text(num, &help));
         return Ok(());
     }
 
@@ -893,13 +2490,13 @@ impl<'a, 'b> HelpFormatter<'a, 'b> {
         try!(self.write_usage());
         try!(write!(self.buf, "\n"));
         if !self.parser.description.is_empty() {
-            try!(wrap_text(self.buf, self.parser.description,TOTAL_WIDTH, 0));
+            try!(wrap_text(self.buf, self.parser.description, self.width, 0));
             try!(write!(self.buf, "\n"));
         }
         if !self.parser.arguments.is_empty()
             || self.parser.catchall_argument.is_some()
         {
-            try!(write!(self.buf, "\nPositional arguments:\n"));
+            try!(self.write_header("Positional arguments:"));
             for arg in self.parser.arguments.iter() {
                 try!(self.print_argument(&**arg));
             }
@@ -910,26 +2507,107 @@ impl<'a, 'b> HelpFormatter<'a, 'b> {
                 None => {}
             }
         }
-        if !self.parser.short_options.is_empty()
-            || !self.parser.long_options.is_empty()
+        if !self.parser.subcommands.is_empty() {
+            try!(self.write_header("Commands:"));
+            for sub in self.parser.subcommands.iter() {
+                try!(self.print_subcommand(&**sub));
+            }
+        }
+        let parser = self.parser;
+        if parser.options.iter()
+            .any(|opt| opt.group.is_none() && parser.is_visible(opt))
         {
-            try!(write!(self.buf, "\nOptional arguments:\n"));
-            for opt in self.parser.options.iter() {
+            try!(self.write_header("Optional arguments:"));
+            for opt in parser.options.iter().filter(|o| o.group.is_none()) {
                 try!(self.print_option(&**opt));
             }
         }
+        for (idx, group) in parser.help_groups.iter().enumerate() {
+            if !parser.options.iter()
+                .any(|opt| opt.group == Some(idx) && parser.is_visible(opt))
+            {
+                continue;
+            }
+            try!(self.write_header(&format!("{}:", group.title)));
+            if !group.description.is_empty() {
+                try!(write!(self.buf, "  "));
+                try!(wrap_text(self.buf, group.description, self.width, 2));
+                try!(write!(self.buf, "\n\n"));
+            }
+            for opt in parser.options.iter().filter(|o| o.group == Some(idx))
+            {
+                try!(self.print_option(&**opt));
+            }
+        }
+        for section in parser.sections.iter() {
+            try!(self.write_header(&format!("{}:", section.title)));
+            if section.verbatim {
+                for line in dedent(section.text) {
+                    if line.is_empty() {
+                        try!(write!(self.buf, "\n"));
+                    } else {
+                        try!(write!(self.buf, "  {}\n", line));
+                    }
+                }
+            } else {
+                try!(wrap_paragraphs(self.buf, section.text, self.width, 2));
+                try!(write!(self.buf, "\n"));
+            }
+        }
+        if !parser.examples.is_empty() {
+            try!(self.write_header("Examples:"));
+            for example in parser.examples.iter() {
+                if !example.description.is_empty() {
+                    try!(write!(self.buf, "  "));
+                    try!(wrap_text(self.buf, example.description,
+                        self.width, 2));
+                    try!(write!(self.buf, "\n"));
+                }
+                try!(write!(self.buf, "    {}\n", example.command));
+            }
+        }
+        if !parser.epilog.is_empty() {
+            try!(write!(self.buf, "\n"));
+            try!(wrap_paragraphs(self.buf, parser.epilog, self.width, 0));
+            try!(write!(self.buf, "\n"));
+        }
+        return Ok(());
+    }
+
+    fn write_header(&mut self, title: &str) -> IoResult<()> {
+        try!(write!(self.buf, "\n"));
+        try!(self.write_styled(|t| &t.header, title));
+        try!(write!(self.buf, "\n"));
         return Ok(());
     }
 
     fn write_usage(&mut self) -> IoResult<()> {
-        try!(write!(self.buf, "Usage:\n  "));
+        try!(self.write_styled(|t| &t.header, "Usage:"));
+        try!(write!(self.buf, "\n  "));
         try!(write!(self.buf, "{}", self.name));
         if !self.parser.options.is_empty() {
-            if self.parser.short_options.len() > 1
-                || self.parser.long_options.len() > 1
-            {
+            if self.parser.has_options() {
                 try!(write!(self.buf, " [OPTIONS]"));
             }
+            for group in self.parser.exclusive_groups.iter() {
+                let names: Vec<_> = group.options.iter()
+                    .filter_map(|&id| {
+                        let names = self.parser.visible_names(
+                            &self.parser.options[id]);
+                        names.iter().find(|x| x.starts_with("--"))
+                            .or(names.first()).map(|x| x.to_string())
+                    })
+                    .collect();
+                // not an alternative with a single visible option
+                if names.len() < 2 {
+                    continue;
+                }
+                if group.required {
+                    try!(write!(self.buf, " ({})", names.join(" | ")));
+                } else {
+                    try!(write!(self.buf, " [{}]", names.join(" | ")));
+                }
+            }
             for opt in self.parser.arguments.iter() {
                 let var = &self.parser.vars[opt.varid];
                 try!(write!(self.buf, " "));
@@ -943,6 +2621,29 @@ impl<'a, 'b> HelpFormatter<'a, 'b> {
                 }
             }
             match self.parser.catchall_argument {
+                Some(ref opt) if self.parser.vars[opt.varid].arity.is_some()
+                => {
+                    let var = &self.parser.vars[opt.varid];
+                    let label = repeat_metavar(
+                        &opt.name.to_ascii_uppercase(), var.arity.unwrap());
+                    // a collection takes the group of values repeatedly
+                    let repeated = match opt.action {
+                        Push(_) => true,
+                        _ => false,
+                    };
+                    if var.required || !self.parser.accepts_values(opt, 0) {
+                        try!(write!(self.buf, " {}", label));
+                        if repeated {
+                            try!(write!(self.buf, " [...]"));
+                        }
+                    } else {
+                        try!(write!(self.buf, " [{}", label));
+                        if repeated {
+                            try!(write!(self.buf, " ..."));
+                        }
+                        try!(write!(self.buf, "]"));
+                    }
+                }
                 Some(ref opt) => {
                     let var = &self.parser.vars[opt.varid];
                     try!(write!(self.buf, " "));
@@ -959,6 +2660,23 @@ impl<'a, 'b> HelpFormatter<'a, 'b> {
                 }
                 None => {}
             }
+            match self.parser.subcommands.first() {
+                Some(sub) => {
+                    let var = &self.parser.vars[sub.varid];
+                    try!(write!(self.buf, " "));
+                    if !var.required {
+                        try!(write!(self.buf, "["));
+                    }
+                    try!(write!(self.buf, "{}",
+                        &var.metavar.to_ascii_uppercase()[..]));
+                    if !var.required {
+                        try!(write!(self.buf, " ...]"));
+                    } else {
+                        try!(write!(self.buf, " [...]"));
+                    }
+                }
+                None => {}
+            }
         }
         try!(write!(self.buf, "\n"));
         return Ok(());
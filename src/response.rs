@@ -0,0 +1,202 @@
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use error::ParseError;
+use parser::os_str_tail;
+
+
+/// How `@path` arguments are expanded
+///
+/// When enabled, each argument starting with `@` (before the `--`
+/// delimiter) is replaced by the arguments read from the file. Files may
+/// include other files the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFiles {
+    /// `@path` is a normal argument (the default)
+    Disabled,
+    /// Each non-empty line of the file is a single argument
+    Lines,
+    /// Arguments are separated by whitespace and may be quoted like in
+    /// the shell: `'single'`, `"double"` or escaped by a backslash. Lines
+    /// starting with `#` are comments
+    Shell,
+}
+
+struct Expander {
+    mode: ResponseFiles,
+    stack: Vec<PathBuf>,
+    delimiter_seen: bool,
+    result: Vec<OsString>,
+}
+
+fn error(path: &Path, line: Option<usize>, message: String) -> ParseError {
+    return ParseError::ResponseFile {
+        path: path.to_string_lossy().into_owned(),
+        line: line,
+        message: message,
+    };
+}
+
+/// Splits line into arguments using shell-like quoting
+///
+/// Unterminated quote continues on the next line, so the state is kept
+/// across calls: `current` is the word being built and `quote` is the
+/// quote character that is still open.
+fn split_shell(line: &str, current: &mut Option<String>,
+    quote: &mut Option<char>, result: &mut Vec<String>)
+{
+    let mut chars = line.chars();
+    if quote.is_none() && line.trim_start().starts_with('#') {
+        return;
+    }
+    loop {
+        let ch = match chars.next() {
+            Some(ch) => ch,
+            None => break,
+        };
+        match (*quote, ch) {
+            (Some('\''), '\'') | (Some('"'), '"') => *quote = None,
+            (Some('\''), _) => current.as_mut().unwrap().push(ch),
+            (Some(_), '\\') => {
+                match chars.next() {
+                    Some(next @ '"') | Some(next @ '\\')
+                    | Some(next @ '$') | Some(next @ '`') => {
+                        current.as_mut().unwrap().push(next);
+                    }
+                    Some(next) => {
+                        current.as_mut().unwrap().push('\\');
+                        current.as_mut().unwrap().push(next);
+                    }
+                    None => current.as_mut().unwrap().push('\n'),
+                }
+            }
+            (Some(_), _) => current.as_mut().unwrap().push(ch),
+            (None, '\'') | (None, '"') => {
+                *quote = Some(ch);
+                current.get_or_insert_with(String::new);
+            }
+            (None, '\\') => {
+                match chars.next() {
+                    Some(next) => {
+                        current.get_or_insert_with(String::new).push(next);
+                    }
+                    None => {}
+                }
+            }
+            (None, ch) if ch.is_whitespace() => {
+                match current.take() {
+                    Some(word) => result.push(word),
+                    None => {}
+                }
+            }
+            (None, _) => current.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    if quote.is_some() {
+        current.as_mut().unwrap().push('\n');
+    } else {
+        match current.take() {
+            Some(word) => result.push(word),
+            None => {}
+        }
+    }
+}
+
+impl Expander {
+    fn add(&mut self, arg: &OsStr, source: Option<(&Path, usize)>)
+        -> Result<(), ParseError>
+    {
+        let bytes = arg.as_encoded_bytes();
+        if self.delimiter_seen || bytes.len() < 2 || bytes[0] != b'@' {
+            if bytes == b"--" {
+                self.delimiter_seen = true;
+            }
+            self.result.push(arg.to_os_string());
+            return Ok(());
+        }
+        let path = Path::new(os_str_tail(arg, 1));
+        let report = |message: String| match source {
+            Some((file, line)) => error(file, Some(line), message),
+            None => error(path, None, message),
+        };
+        let canonical = try!(path.canonicalize()
+            .map_err(|e| report(format!("can't open {:?}: {}", path, e))));
+        if self.stack.contains(&canonical) {
+            return Err(report(format!("recursive inclusion of {:?}", path)));
+        }
+        let mut data = Vec::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_end(&mut data))
+            .map_err(|e| report(format!("can't read {:?}: {}", path, e))));
+        self.stack.push(canonical);
+        try!(self.add_file(path, &data));
+        self.stack.pop();
+        return Ok(());
+    }
+
+    fn add_file(&mut self, path: &Path, data: &[u8])
+        -> Result<(), ParseError>
+    {
+        let mut current = None;
+        let mut quote = None;
+        let mut quote_line = 0;
+        for (idx, line) in data.split(|&b| b == b'\n').enumerate() {
+            let lineno = idx + 1;
+            let line = if line.ends_with(b"\r") {
+                &line[..line.len()-1]
+            } else {
+                line
+            };
+            let line = try!(::std::str::from_utf8(line)
+                .map_err(|_| error(path, Some(lineno),
+                                   "invalid utf-8".to_string())));
+            let mut args = Vec::new();
+            match self.mode {
+                ResponseFiles::Disabled => unreachable!(),
+                ResponseFiles::Lines => {
+                    if !line.is_empty() {
+                        args.push(line.to_string());
+                    }
+                }
+                ResponseFiles::Shell => {
+                    if quote.is_none() {
+                        quote_line = lineno;
+                    }
+                    split_shell(line, &mut current, &mut quote, &mut args);
+                }
+            }
+            for arg in args.iter() {
+                try!(self.add(OsStr::new(arg), Some((path, lineno))));
+            }
+        }
+        if quote.is_some() {
+            return Err(error(path, Some(quote_line),
+                             "unterminated quote".to_string()));
+        }
+        return Ok(());
+    }
+}
+
+/// Replaces `@path` arguments with the contents of the files
+///
+/// The first argument (the program name) is never expanded
+pub fn expand(args: &[OsString], mode: ResponseFiles)
+    -> Result<Vec<OsString>, ParseError>
+{
+    let mut expander = Expander {
+        mode: mode,
+        stack: Vec::new(),
+        delimiter_seen: false,
+        result: Vec::new(),
+    };
+    let mut iter = args.iter();
+    match iter.next() {
+        Some(name) => expander.result.push(name.clone()),
+        None => {}
+    }
+    for arg in iter {
+        try!(expander.add(arg, None));
+    }
+    return Ok(expander.result);
+}
@@ -0,0 +1,114 @@
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use action::IArgAction;
+use action::ParseResult;
+use action::ParseResult::{Parsed, BadValue};
+
+
+/// When to use colors in help, usage and error messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Use colors if the output is a terminal and `NO_COLOR` is not set
+    /// (the default)
+    Auto,
+    /// Always use colors
+    Always,
+    /// Never use colors
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+    fn from_str(src: &str) -> Result<ColorChoice, String> {
+        match src {
+            "auto" => return Ok(ColorChoice::Auto),
+            "always" => return Ok(ColorChoice::Always),
+            "never" => return Ok(ColorChoice::Never),
+            _ => return Err("expected auto, always or never".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.write_str(match *self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        });
+    }
+}
+
+impl ColorChoice {
+    /// Whether colors should be used for an output
+    ///
+    /// `terminal` tells whether the output is a terminal
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => return true,
+            ColorChoice::Never => return false,
+            ColorChoice::Auto => {
+                return terminal && env::var_os("NO_COLOR")
+                    .map(|x| x.is_empty()).unwrap_or(true);
+            }
+        }
+    }
+}
+
+/// Styles of the parts of help, usage and error messages
+///
+/// Each style is a list of ANSI SGR parameters, e.g. `"1;31"` for bold
+/// red. An empty string leaves the part unstyled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Section headers: `Usage:`, `Optional arguments:`...
+    pub header: String,
+    /// Option names, argument names and commands
+    pub option: String,
+    /// Metavars of the options
+    pub metavar: String,
+    /// The `command:` prefix of error messages
+    pub error: String,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        return Theme {
+            header: "1;4".to_string(),
+            option: "1".to_string(),
+            metavar: "3".to_string(),
+            error: "1;31".to_string(),
+        };
+    }
+}
+
+impl Theme {
+    /// Wraps text into the escape sequences of the style
+    pub(crate) fn paint(style: &str, text: &str) -> String {
+        if style.is_empty() {
+            return text.to_string();
+        }
+        return format!("\x1b[{}m{}\x1b[0m", style, text);
+    }
+}
+
+/// Action of the `--color` option, see `ArgumentParser::add_color_option`
+pub struct ColorAction {
+    pub cell: Rc<Cell<Option<ColorChoice>>>,
+}
+
+impl IArgAction for ColorAction {
+    fn parse_arg(&self, arg: &str) -> ParseResult {
+        match arg.parse() {
+            Ok(choice) => {
+                self.cell.set(Some(choice));
+                return Parsed;
+            }
+            Err(e) => return BadValue(arg.to_string(), Some(e)),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// Edit distance between two strings (counted in chars)
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..b.len()+1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let cur = row[j+1];
+            row[j+1] = if ca == cb {
+                prev
+            } else {
+                1 + prev.min(cur).min(row[j])
+            };
+            prev = cur;
+        }
+    }
+    return row[b.len()];
+}
+
+/// Finds the candidate closest to the `name` if it's close enough
+///
+/// A candidate is close enough if it differs in no more than a third of
+/// the characters (but at least one character is always allowed).
+pub fn did_you_mean<'a, I>(name: &str, candidates: I) -> Option<String>
+    where I: IntoIterator<Item=&'a str>
+{
+    let limit = ::std::cmp::max(1, name.chars().count() / 3);
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        let distance = levenshtein(name, candidate);
+        if distance > limit {
+            continue;
+        }
+        match best {
+            Some((best_distance, _)) if best_distance <= distance => {}
+            _ => best = Some((distance, candidate)),
+        }
+    }
+    return best.map(|(_, candidate)| candidate.to_string());
+}
@@ -0,0 +1,44 @@
+use std::env;
+
+
+#[cfg(unix)]
+mod ioctl {
+    use libc;
+
+    /// Width of the terminal the file descriptor refers to
+    pub fn width(fd: libc::c_int) -> Option<usize> {
+        let mut size = libc::winsize {
+            ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0,
+        };
+        let res = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+        if res == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+        return None;
+    }
+}
+
+#[cfg(not(unix))]
+mod ioctl {
+    pub fn width(_fd: i32) -> Option<usize> {
+        return None;
+    }
+}
+
+/// Parse the value of the `COLUMNS` environment variable
+pub fn parse_columns(value: &str) -> Option<usize> {
+    return value.trim().parse().ok().and_then(|x| {
+        if x > 0 { Some(x) } else { None }
+    });
+}
+
+/// Width of the terminal for help output
+///
+/// Asks the terminal on stdout (or stderr), then falls back to the
+/// `COLUMNS` environment variable. Returns `None` if neither is known.
+pub fn width() -> Option<usize> {
+    return ioctl::width(1)
+        .or_else(|| ioctl::width(2))
+        .or_else(|| env::var("COLUMNS").ok()
+                    .and_then(|x| parse_columns(&x)));
+}
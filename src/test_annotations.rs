@@ -0,0 +1,69 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use completion::Completion;
+use super::{Store, StoreOption};
+
+fn help(show: bool) -> String {
+    let mut port = 8080u16;
+    let mut host: Option<String> = None;
+    let mut mode = "fast".to_string();
+    let mut name = "world".to_string();
+    let mut ap = ArgumentParser::new();
+    ap.show_defaults(show);
+    ap.show_env_vars(show);
+    ap.show_possible_values(show);
+    ap.refer(&mut port)
+        .add_option(&["--port"], Store, "Port to listen")
+        .envvar("TEST_PORT")
+        .envvar("PORT")
+        .show_default();
+    ap.refer(&mut host)
+        .add_option(&["--host"], StoreOption, "")
+        .show_default_debug();
+    ap.refer(&mut mode)
+        .add_option(&["--mode"], Store, "Mode")
+        .completion(Completion::Values(
+            vec!["fast".to_string(), "slow".to_string()]))
+        .show_default();
+    ap.refer(&mut name)
+        .add_argument("name", Store, "Name to greet")
+        .show_default();
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    return from_utf8(&buf[..]).unwrap().to_string();
+}
+
+#[test]
+fn test_annotations() {
+    assert_eq!(help(true), "Usage:
+  ./argparse_test [OPTIONS] [NAME]
+
+
+Positional arguments:
+  name                  Name to greet [default: world]
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  --port PORT           Port to listen [default: 8080] [env: TEST_PORT, PORT]
+  --host HOST           [default: None]
+  --mode MODE           Mode [default: fast] [possible values: fast, slow]
+");
+}
+
+#[test]
+fn test_disabled() {
+    assert_eq!(help(false), "Usage:
+  ./argparse_test [OPTIONS] [NAME]
+
+
+Positional arguments:
+  name                  Name to greet
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  --port PORT           Port to listen
+  --host HOST          \x20
+  --mode MODE           Mode
+");
+}
@@ -0,0 +1,72 @@
+use std::str::from_utf8;
+use std::path::PathBuf;
+
+use parser::ArgumentParser;
+use completion::{Completion, Shell};
+use super::{Store, Parse, StoreTrue};
+
+fn completion(shell: Shell) -> String {
+    let mut verbose = false;
+    let mut mode = String::new();
+    let mut output = PathBuf::new();
+    let mut command = String::new();
+    let mut input = PathBuf::new();
+    let mut play = ArgumentParser::new();
+    play.refer(&mut input)
+        .add_argument("input", Parse, "Input file");
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut verbose)
+        .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+    ap.refer(&mut output)
+        .add_option(&["-o", "--output"], Parse, "Output file");
+    ap.refer(&mut mode)
+        .add_option(&["--mode"], Store, "Mode")
+        .completion(Completion::Values(
+            vec!["fast".to_string(), "slow".to_string()]));
+    ap.refer(&mut command)
+        .add_subcommand("play", "Play a sound", play);
+    let mut buf = Vec::<u8>::new();
+    assert!(ap.print_completion(shell, "prog", &mut buf).is_ok());
+    return from_utf8(&buf[..]).unwrap().to_string();
+}
+
+#[test]
+fn test_fish() {
+    assert_eq!(completion(Shell::Fish), "".to_string()
+        + "complete -c prog -n '__fish_use_subcommand' -s h -l help"
+        + " -d 'Show this help message and exit'\n"
+        + "complete -c prog -n '__fish_use_subcommand' -s v -l verbose"
+        + " -d 'Be verbose'\n"
+        + "complete -c prog -n '__fish_use_subcommand' -s o -l output"
+        + " -r -F -d 'Output file'\n"
+        + "complete -c prog -n '__fish_use_subcommand' -l mode"
+        + " -r -f -a 'fast slow' -d 'Mode'\n"
+        + "complete -c prog -n '__fish_use_subcommand' -f -a 'play'"
+        + " -d 'Play a sound'\n"
+        + "complete -c prog -n '__fish_seen_subcommand_from play'"
+        + " -s h -l help -d 'Show this help message and exit'\n"
+        + "complete -c prog -n '__fish_seen_subcommand_from play' -F\n");
+}
+
+#[test]
+fn test_zsh() {
+    let script = completion(Shell::Zsh);
+    assert!(script.starts_with("#compdef prog\n"));
+    assert!(script.contains(
+        "'(-o --output)'{-o+,--output=}'[Output file]:OUTPUT:_files'"));
+    assert!(script.contains("--mode='[Mode]:MODE:(fast slow)'"));
+    assert!(script.contains("'play:Play a sound'"));
+    assert!(script.contains("_prog_play() {"));
+    assert!(script.contains("'1:input:_files'"));
+}
+
+#[test]
+fn test_bash() {
+    let script = completion(Shell::Bash);
+    assert!(script.starts_with("_prog() {\n"));
+    assert!(script.contains("'prog:play')  cmd=prog_play ;;"));
+    assert!(script.contains("COMPREPLY=($(compgen -W 'fast slow' -- \"$cur\"))"));
+    assert!(script.contains(
+        "compgen -W '-h --help -v --verbose -o --output --mode'"));
+    assert!(script.ends_with("complete -o default -F _prog 'prog'\n"));
+}
@@ -0,0 +1,143 @@
+use std::env;
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use config::{Config, ConfigError};
+use super::{Store, StoreTrue, Collect};
+
+const CONFIG: &'static str = "\
+# Test config
+verbose = true
+name = \"John \\\"Doe\\\"\"  # comment
+
+[server]
+port = 8080
+host = 'local # host'
+";
+
+fn parse(config: &Config, args: &[&str])
+    -> (bool, String, u16, String, String)
+{
+    let mut verbose = false;
+    let mut name = "World".to_string();
+    let mut port = 80u16;
+    let mut host = "localhost".to_string();
+    let mut stderr = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_config(config);
+        ap.refer(&mut verbose)
+            .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose")
+            .config_key("verbose", Store);
+        ap.refer(&mut name)
+            .add_option(&["--name"], Store, "Name")
+            .config_key("name", Store);
+        ap.refer(&mut port)
+            .add_option(&["--port"], Store, "Port")
+            .envvar("TEST_CONFIG_PORT")
+            .config_key("server.port", Store);
+        ap.refer(&mut host)
+            .add_option(&["--host"], Store, "Host")
+            .config_key("server.host", Store);
+        let args = args.iter().map(|x| x.to_string()).collect();
+        ap.parse(args, &mut Vec::new(), &mut stderr).unwrap();
+    }
+    return (verbose, name, port, host,
+            from_utf8(&stderr[..]).unwrap().to_string());
+}
+
+#[test]
+fn test_config() {
+    let config = Config::parse(CONFIG).unwrap();
+    assert_eq!(config.get("server.port"), Some("8080"));
+    assert_eq!(parse(&config, &["./argparse_test"]),
+        (true, "John \"Doe\"".to_string(), 8080,
+         "local # host".to_string(), "".to_string()));
+}
+
+#[test]
+fn test_precedence() {
+    let mut config = Config::parse(CONFIG).unwrap();
+    config.set("server.host", "example.com");
+    env::set_var("TEST_CONFIG_PORT", "8000");
+    let result = parse(&config, &["./argparse_test", "--name=Bob"]);
+    env::remove_var("TEST_CONFIG_PORT");
+    assert_eq!(result,
+        (true, "Bob".to_string(), 8000,
+         "example.com".to_string(), "".to_string()));
+}
+
+#[test]
+fn test_bad_value() {
+    let config = Config::parse("[server]\nport = x\n").unwrap();
+    assert_eq!(parse(&config, &["./argparse_test"]),
+        (false, "World".to_string(), 80, "localhost".to_string(),
+         "WARNING: Config <config>:2: server.port: Bad value x\n"
+         .to_string()));
+}
+
+fn parse_positional(config: &Config, args: &[&str])
+    -> Result<(String, Vec<String>), String>
+{
+    let mut name = "World".to_string();
+    let mut tags = Vec::<String>::new();
+    let mut stderr = Vec::<u8>::new();
+    let res = {
+        let mut ap = ArgumentParser::new();
+        ap.set_config(config);
+        ap.refer(&mut name)
+            .add_argument("name", Store, "Name")
+            .config_key("name", Store);
+        ap.refer(&mut tags)
+            .add_option(&["--tag"], Collect, "Tag")
+            .delimiter(',')
+            .config_key("tags", Collect);
+        let args = args.iter().map(|x| x.to_string()).collect();
+        ap.parse(args, &mut Vec::new(), &mut stderr)
+    };
+    return match res {
+        Ok(()) => Ok((name, tags)),
+        Err(_) => Err(from_utf8(&stderr[..]).unwrap().to_string()),
+    };
+}
+
+#[test]
+fn test_positional() {
+    let config = Config::parse("name = Bob\ntags = a,b\n").unwrap();
+    assert_eq!(parse_positional(&config, &["./argparse_test"]),
+        Ok(("Bob".to_string(), vec!["a".to_string(), "b".to_string()])));
+    assert_eq!(parse_positional(&config, &["./argparse_test", "Alice"]),
+        Ok(("Alice".to_string(), vec!["a".to_string(), "b".to_string()])));
+    assert_eq!(parse_positional(&config,
+                                &["./argparse_test", "Alice", "--tag=c"]),
+        Ok(("Alice".to_string(), vec!["c".to_string()])));
+}
+
+#[test]
+fn test_syntax_errors() {
+    fn error(text: &str) -> (Option<usize>, String) {
+        let err = Config::parse(text).err().unwrap();
+        return (err.line, err.message);
+    }
+    assert_eq!(error("a = 1\n\nb\n"),
+        (Some(3), "expected `key = value`".to_string()));
+    assert_eq!(error("[a\n"), (Some(1), "expected `]`".to_string()));
+    assert_eq!(error("a = \"x\n"),
+        (Some(1), "unterminated string".to_string()));
+    assert_eq!(error("a = 'x' y\n"),
+        (Some(1), "unexpected \"y\" after the value".to_string()));
+    assert_eq!(error("[x]\na = 1\n[x]\na = 2\n"),
+        (Some(4), "duplicate key x.a".to_string()));
+    assert_eq!(Config::parse(" = 1").err().unwrap().to_string(),
+        "<config>:1: empty key");
+}
+
+#[test]
+fn test_missing_file() {
+    match Config::from_file("/nonexistent/argparse.ini") {
+        Err(ConfigError { path: Some(ref path), line: None, .. }) => {
+            assert_eq!(path, "/nonexistent/argparse.ini");
+        }
+        _ => panic!("Error expected"),
+    }
+}
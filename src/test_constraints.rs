@@ -0,0 +1,91 @@
+use parser::ArgumentParser;
+use error::ParseError;
+use super::{Store, StoreTrue};
+
+fn try_parse(ap: &ArgumentParser, args: &[&str]) -> Result<(), ParseError> {
+    let owned_args = args.iter().map(|x| x.to_string()).collect();
+    return ap.try_parse(owned_args, &mut Vec::new(), &mut Vec::new());
+}
+
+#[test]
+fn test_requires() {
+    let mut key = String::new();
+    let mut cert = String::new();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut key)
+        .add_option(&["-k", "--tls-key"], Store, "Key")
+        .requires("--tls-cert");
+    ap.refer(&mut cert)
+        .add_option(&["--tls-cert"], Store, "Certificate");
+    assert_eq!(try_parse(&ap, &["./argparse_test"]), Ok(()));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--tls-cert=a"]), Ok(()));
+    assert_eq!(try_parse(&ap, &["./argparse_test",
+                                "--tls-key=a", "--tls-cert=b"]), Ok(()));
+    let err = try_parse(&ap, &["./argparse_test", "-k", "a"]).unwrap_err();
+    assert_eq!(err, ParseError::MissingRequirement {
+        option: "-k".to_string(),
+        requirement: "--tls-cert".to_string(),
+    });
+    assert_eq!(err.to_string(), "-k requires --tls-cert");
+}
+
+#[test]
+fn test_conflicts_with() {
+    let mut dry_run = false;
+    let mut force = false;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut dry_run)
+        .add_option(&["-n", "--dry-run"], StoreTrue, "Dry run")
+        .conflicts_with("--force");
+    ap.refer(&mut force)
+        .add_option(&["-f", "--force"], StoreTrue, "Force");
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-n"]), Ok(()));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-f"]), Ok(()));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-f", "--dry-run"]),
+        Err(ParseError::ConflictingOptions {
+            option: "--dry-run".to_string(),
+            other: "-f".to_string(),
+        }));
+}
+
+#[test]
+fn test_required_if() {
+    let mut output = false;
+    let mut file = String::new();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut output)
+        .add_option(&["-o", "--output"], StoreTrue, "Write output");
+    ap.refer(&mut file)
+        .add_argument("file", Store, "Output file")
+        .required_if("--output");
+    assert_eq!(try_parse(&ap, &["./argparse_test"]), Ok(()));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-o", "x"]), Ok(()));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-o"]),
+        Err(ParseError::MissingRequirement {
+            option: "-o".to_string(),
+            requirement: "file".to_string(),
+        }));
+}
+
+#[test]
+#[should_panic(expected="Unknown option or argument --tls-cert")]
+fn test_unknown() {
+    let mut key = String::new();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut key)
+        .add_option(&["--tls-key"], Store, "Key")
+        .requires("--tls-cert");
+    try_parse(&ap, &["./argparse_test", "--tls-key=x"]).ok();
+}
+
+#[test]
+#[should_panic(expected="Unknown option or argument --dry-rn")]
+fn test_unknown_unused() {
+    let mut force = false;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut force)
+        .add_option(&["--force"], StoreTrue, "Force")
+        .conflicts_with("--dry-rn");
+    // panics even if the constrained option isn't used
+    try_parse(&ap, &["./argparse_test"]).ok();
+}
@@ -0,0 +1,125 @@
+use std::env;
+use std::path::PathBuf;
+
+use parser::ArgumentParser;
+use super::{Collect, List, Parse, ParseCollect};
+use test_parser::{check_ok, check_result};
+
+fn tags(args: &[&str]) -> Vec<String> {
+    let mut tags = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut tags)
+          .add_option(&["-t", "--tags"], Collect, "Tags")
+          .delimiter(',')
+          .envvar_list("TEST_DELIMITER_TAGS");
+        check_ok(&ap, args);
+    }
+    return tags;
+}
+
+#[test]
+fn test_split() {
+    assert_eq!(tags(&["./argparse_test"]), Vec::<String>::new());
+    assert_eq!(tags(&["./argparse_test", "--tags", "a,b,c"]),
+        vec!("a", "b", "c"));
+    assert_eq!(tags(&["./argparse_test", "-ta,b", "--tags=c"]),
+        vec!("a", "b", "c"));
+    assert_eq!(tags(&["./argparse_test", "--tags", "a,,b"]),
+        vec!("a", "", "b"));
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(tags(&["./argparse_test", "--tags", r"a\,b,c"]),
+        vec!("a,b", "c"));
+    assert_eq!(tags(&["./argparse_test", "--tags", r"a\\,b"]),
+        vec!(r"a\", "b"));
+    assert_eq!(tags(&["./argparse_test", "--tags", r"C:\dir"]),
+        vec!(r"C:\dir"));
+}
+
+#[test]
+fn test_envvar() {
+    env::set_var("TEST_DELIMITER_TAGS", r"x,y\,z");
+    let from_env = tags(&["./argparse_test"]);
+    let overridden = tags(&["./argparse_test", "--tags", "a"]);
+    env::remove_var("TEST_DELIMITER_TAGS");
+    assert_eq!(from_env, vec!("x", "y,z"));
+    assert_eq!(overridden, vec!("a"));
+}
+
+#[test]
+fn test_envvar_no_delimiter() {
+    let mut nums: Vec<u32> = vec!();
+    env::set_var("TEST_DELIMITER_NUMS", "12");
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut nums)
+          .add_option(&["--num"], Collect, "Numbers")
+          .envvar_list("TEST_DELIMITER_NUMS");
+        check_ok(&ap, &["./argparse_test"]);
+    }
+    env::remove_var("TEST_DELIMITER_NUMS");
+    assert_eq!(nums, vec!(12));
+}
+
+#[test]
+fn test_positional() {
+    let mut paths: Vec<PathBuf> = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut paths)
+          .add_argument("paths", List, "Paths")
+          .delimiter(':');
+        check_ok(&ap, &["./argparse_test", "/bin:/usr/bin", "/sbin"]);
+    }
+    assert_eq!(paths, vec!(PathBuf::from("/bin"), PathBuf::from("/usr/bin"),
+                           PathBuf::from("/sbin")));
+}
+
+#[test]
+fn test_parse_collect() {
+    let mut paths: Vec<PathBuf> = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut paths)
+          .add_option(&["--path"], ParseCollect, "Paths")
+          .delimiter(':');
+        check_ok(&ap, &["./argparse_test", "--path", "/bin:/usr/bin"]);
+    }
+    assert_eq!(paths, vec!(PathBuf::from("/bin"), PathBuf::from("/usr/bin")));
+}
+
+fn point(args: &[&str]) -> Result<(Vec<u32>, (u32, u32)), String> {
+    let mut point = vec!();
+    let mut size = (0, 0);
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut point)
+          .add_option(&["--point"], List, "Point")
+          .nargs(2)
+          .delimiter(',');
+        ap.refer(&mut size)
+          .add_argument("size", Parse, "Size")
+          .delimiter('x');
+        try!(check_result(&ap, args));
+    }
+    return Ok((point, size));
+}
+
+#[test]
+fn test_nargs() {
+    assert_eq!(point(&["./argparse_test", "--point", "1,2", "3x4"]),
+        Ok((vec!(1, 2), (3, 4))));
+    assert_eq!(point(&["./argparse_test", "--point", "1", "2", "3", "4"]),
+        Ok((vec!(1, 2), (3, 4))));
+    assert_eq!(point(&["./argparse_test", "--point=1", "2,3"]),
+        Err("./argparse_test: --point requires 2 values, got 3"
+            .to_string()));
+    assert_eq!(point(&["./argparse_test", "--point", "1,2,3"]),
+        Err("./argparse_test: --point requires 2 values, got 3"
+            .to_string()));
+    assert_eq!(point(&["./argparse_test", "3x4x5"]),
+        Err("./argparse_test: size requires 2 values, got 3".to_string()));
+}
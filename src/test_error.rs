@@ -0,0 +1,161 @@
+use std::str::FromStr;
+
+use parser::ArgumentParser;
+use error::ParseError;
+use super::{Store, Parse, StoreTrue, Collect};
+use completion::Completion;
+
+fn try_parse(ap: &ArgumentParser, args: &[&str]) -> Result<(), ParseError> {
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    let owned_args = args.iter().map(|x| x.to_string()).collect();
+    return ap.try_parse(owned_args, &mut stdout, &mut stderr);
+}
+
+#[test]
+fn test_unknown() {
+    let ap = ArgumentParser::new();
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--verbose"]),
+        Err(ParseError::UnknownOption {
+            option: "--verbose".to_string(),
+            suggestion: None,
+        }));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-x"]),
+        Err(ParseError::UnknownOption {
+            option: "-x".to_string(),
+            suggestion: None,
+        }));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "arg"]),
+        Err(ParseError::UnexpectedArgument("arg".to_string())));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--help"]),
+        Err(ParseError::Help));
+}
+
+#[test]
+fn test_bad_value() {
+    let mut val = 0u8;
+    let mut port = 0u16;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut val)
+        .add_option(&["-v", "--value"], Store, "Value");
+    ap.refer(&mut port)
+        .add_argument("port", Parse, "Port");
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-vx"]),
+        Err(ParseError::BadValue {
+            name: "-v".to_string(),
+            value: "x".to_string(),
+            metavar: "VALUE".to_string(),
+            reason: None,
+            suggestion: None,
+        }));
+    let err = try_parse(&ap, &["./argparse_test", "100000"]).unwrap_err();
+    match err {
+        ParseError::BadValue { ref name, ref value, reason: Some(_), .. } => {
+            assert_eq!(name, "port");
+            assert_eq!(value, "100000");
+        }
+        _ => panic!("Unexpected error {:?}", err),
+    }
+    assert_eq!(err.exit_code(), 2);
+}
+
+#[test]
+fn test_values() {
+    let mut flag = false;
+    let mut val = 0;
+    let mut lst = Vec::<u32>::new();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut flag)
+        .add_option(&["--flag"], StoreTrue, "Flag");
+    ap.refer(&mut val)
+        .add_option(&["--value"], Store, "Value")
+        .required();
+    ap.refer(&mut lst)
+        .add_option(&["--item"], Collect, "Item");
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--flag=1"]),
+        Err(ParseError::UnexpectedValue {
+            option: "--flag".to_string(),
+            value: "1".to_string(),
+        }));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--value"]),
+        Err(ParseError::MissingValue(vec!["--value".to_string()])));
+    assert_eq!(try_parse(&ap, &["./argparse_test"]),
+        Err(ParseError::MissingOption(vec!["--value".to_string()])));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--value=1",
+                                "--item=a"]).unwrap_err().to_string(),
+        "Bad value a");
+}
+
+#[derive(Clone, Copy)]
+enum Color { Always, Never }
+
+impl FromStr for Color {
+    type Err = ();
+    fn from_str(src: &str) -> Result<Color, ()> {
+        return match src {
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(()),
+        };
+    }
+}
+
+#[test]
+fn test_suggestions() {
+    let mut verbose = false;
+    let mut color = Color::Always;
+    let mut command = String::new();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut verbose)
+        .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+    ap.refer(&mut color)
+        .add_option(&["--color"], Store, "Color")
+        .completion(Completion::Values(vec!["always".to_string(),
+                                            "never".to_string()]));
+    ap.refer(&mut command)
+        .add_subcommand("play", "Play", ArgumentParser::new())
+        .add_subcommand("record", "Record", ArgumentParser::new());
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--verbse"])
+        .unwrap_err().to_string(),
+        "Unknown option --verbse, did you mean --verbose?");
+    assert_eq!(try_parse(&ap, &["./argparse_test", "-verbose"]),
+        Err(ParseError::UnknownOption {
+            option: "-e".to_string(),
+            suggestion: Some("--verbose".to_string()),
+        }));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--colour=never"])
+        .unwrap_err().to_string(),
+        "Unknown option --colour=never, did you mean --color?");
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--xyz"]),
+        Err(ParseError::UnknownOption {
+            option: "--xyz".to_string(),
+            suggestion: None,
+        }));
+    assert_eq!(try_parse(&ap, &["./argparse_test", "--color=alway"])
+        .unwrap_err().to_string(),
+        "Bad value alway, did you mean always?");
+    assert_eq!(try_parse(&ap, &["./argparse_test", "recrd"]),
+        Err(ParseError::UnknownCommand {
+            command: "recrd".to_string(),
+            suggestion: Some("record".to_string()),
+        }));
+}
+
+#[test]
+fn test_suggestion_order() {
+    // every parser has a differently seeded hash map of options
+    for _ in 0..10 {
+        let mut vals = vec![false; 4];
+        let mut ap = ArgumentParser::new();
+        for (val, name) in vals.iter_mut()
+            .zip(["--tab", "--tac", "--tad", "--taf"].iter())
+        {
+            ap.refer(val).add_option(&[name], StoreTrue, "Flag");
+        }
+        assert_eq!(try_parse(&ap, &["./argparse_test", "--tax"]),
+            Err(ParseError::UnknownOption {
+                option: "--tax".to_string(),
+                suggestion: Some("--tab".to_string()),
+            }));
+    }
+}
@@ -0,0 +1,115 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use error::ParseError;
+use super::{StoreConst, StoreTrue};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format { Text, Json, Yaml }
+
+fn parse_format(args: &[&str], required: bool)
+    -> Result<(Format, bool), ParseError>
+{
+    let mut format = Format::Text;
+    let mut verbose = false;
+    let res = {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut format)
+            .add_option(&["-j", "--json"], StoreConst(Format::Json), "JSON")
+            .add_option(&["--yaml"], StoreConst(Format::Yaml), "YAML")
+            .add_option(&["--text"], StoreConst(Format::Text), "Text");
+        ap.refer(&mut verbose)
+            .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+        ap.add_exclusive_group(&["--json", "--yaml", "--text"], required);
+        let args = args.iter().map(|x| x.to_string()).collect();
+        ap.try_parse(args, &mut Vec::new(), &mut Vec::new())
+    };
+    return res.map(|()| (format, verbose));
+}
+
+#[test]
+fn test_single() {
+    assert_eq!(parse_format(&["./argparse_test"], false),
+        Ok((Format::Text, false)));
+    assert_eq!(parse_format(&["./argparse_test", "-vj"], false),
+        Ok((Format::Json, true)));
+    assert_eq!(parse_format(&["./argparse_test", "--yaml"], true),
+        Ok((Format::Yaml, false)));
+    assert_eq!(parse_format(&["./argparse_test", "--json", "--json"], false),
+        Ok((Format::Json, false)));
+}
+
+#[test]
+fn test_conflict() {
+    let err = parse_format(&["./argparse_test", "-j", "-v", "--yaml"], false)
+        .unwrap_err();
+    assert_eq!(err, ParseError::ConflictingOptions {
+        option: "--yaml".to_string(),
+        other: "-j".to_string(),
+    });
+    assert_eq!(err.to_string(), "Option --yaml cannot be used with -j");
+}
+
+#[test]
+fn test_required() {
+    assert_eq!(parse_format(&["./argparse_test", "-v"], true),
+        Err(ParseError::MissingOption(vec![
+            "-j".to_string(), "--json".to_string(),
+            "--yaml".to_string(), "--text".to_string()])));
+}
+
+#[test]
+fn test_usage() {
+    let mut json = false;
+    let mut yaml = false;
+    let mut quiet = false;
+    let mut verbose = false;
+    let mut buf = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut json).add_option(&["--json"], StoreTrue, "JSON");
+        ap.refer(&mut yaml).add_option(&["--yaml"], StoreTrue, "YAML");
+        ap.refer(&mut quiet).add_option(&["-q"], StoreTrue, "Quiet");
+        ap.refer(&mut verbose).add_option(&["-v"], StoreTrue, "Verbose");
+        ap.add_exclusive_group(&["--json", "--yaml"], false);
+        ap.add_exclusive_group(&["-q", "-v"], true);
+        assert!(ap.print_usage("./argparse_test", &mut buf).is_ok());
+    }
+    assert_eq!("Usage:\n  ./argparse_test [OPTIONS] [--json | --yaml] \
+                (-q | -v)\n",
+        from_utf8(&buf[..]).unwrap());
+}
+
+#[test]
+fn test_usage_hidden() {
+    let mut old = false;
+    let mut new = false;
+    let mut fast = false;
+    let mut json = false;
+    let mut yaml = false;
+    let mut buf = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut old).add_option(&["--old"], StoreTrue, "Old");
+        ap.refer(&mut new).add_option(&["--new"], StoreTrue, "New");
+        ap.refer(&mut fast)
+            .add_option(&["--fast", "--quick"], StoreTrue, "Fast");
+        ap.refer(&mut json).add_option(&["--json"], StoreTrue, "JSON");
+        ap.refer(&mut yaml).add_option(&["--yaml"], StoreTrue, "YAML");
+        ap.hide_option("--old");
+        ap.hide_option("--fast");
+        ap.add_exclusive_group(&["--old", "--new"], false);
+        ap.add_exclusive_group(&["--quick", "--json", "--yaml"], true);
+        assert!(ap.print_usage("./argparse_test", &mut buf).is_ok());
+    }
+    assert_eq!("Usage:\n  ./argparse_test [OPTIONS] \
+                (--quick | --json | --yaml)\n",
+        from_utf8(&buf[..]).unwrap());
+}
+
+#[test]
+#[should_panic(expected="Unknown option --xml in exclusive group")]
+fn test_unknown_option() {
+    let mut ap = ArgumentParser::new();
+    ap.add_exclusive_group(&["--xml"], false);
+}
@@ -0,0 +1,66 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use super::{Store, StoreTrue};
+
+#[test]
+fn test_groups() {
+    let mut verbose = false;
+    let mut host = String::new();
+    let mut port = 0u16;
+    let mut json = false;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut verbose)
+        .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+    ap.add_group("Network options",
+        "Options that control how the server is reached");
+    ap.refer(&mut host)
+        .add_option(&["--host"], Store, "Server host");
+    ap.refer(&mut port)
+        .add_option(&["--port"], Store, "Server port");
+    ap.add_group("Empty", "Not shown");
+    ap.add_group("Output", "");
+    ap.refer(&mut json)
+        .add_option(&["--json"], StoreTrue, "Print JSON");
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    assert_eq!(from_utf8(&buf[..]).unwrap(), "Usage:
+  ./argparse_test [OPTIONS]
+
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  -v,--verbose          Be verbose
+
+Network options:
+  Options that control how the server is reached
+
+  --host HOST           Server host
+  --port PORT           Server port
+
+Output:
+  --json                Print JSON
+");
+    let mut buf = Vec::<u8>::new();
+    ap.print_man_page("argparse_test", "1", &mut buf).unwrap();
+    let man = from_utf8(&buf[..]).unwrap();
+    assert!(man.contains(".SS Network options\n\
+        Options that control how the server is reached\n\
+        .TP\n\\fB\\-\\-host\\fR"), "{}", man);
+    assert!(!man.contains("Empty"));
+}
+
+#[test]
+fn test_parse() {
+    let mut host = String::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.add_group("Network options", "");
+        ap.refer(&mut host)
+            .add_option(&["--host"], Store, "Server host");
+        ap.parse(vec!["./argparse_test".to_string(),
+                      "--host=example.com".to_string()],
+                 &mut Vec::new(), &mut Vec::new()).unwrap();
+    }
+    assert_eq!(host, "example.com");
+}
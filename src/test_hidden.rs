@@ -0,0 +1,80 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use completion::Shell;
+use super::{Store, StoreTrue};
+
+fn parse(args: &[&str]) -> (bool, String, String, String) {
+    let mut verbose = false;
+    let mut output = String::new();
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut verbose)
+            .add_option(&["-v", "--verbose", "--talkative"], StoreTrue,
+                "Be verbose");
+        ap.refer(&mut output)
+            .add_option(&["-o", "--output", "--out-file"], Store,
+                "Output file")
+            .metavar("FILE");
+        ap.hide_option("--talkative");
+        ap.deprecate_option("--talkative", "");
+        ap.hide_option("--out-file");
+        ap.deprecate_option("--out-file", "use --output instead");
+        let args = args.iter().map(|x| x.to_string()).collect();
+        ap.parse(args, &mut stdout, &mut stderr).ok();
+    }
+    return (verbose, output,
+            from_utf8(&stdout[..]).unwrap().to_string(),
+            from_utf8(&stderr[..]).unwrap().to_string());
+}
+
+#[test]
+fn test_help() {
+    assert_eq!(parse(&["./argparse_test", "--help"]).2, "Usage:
+  ./argparse_test [OPTIONS]
+
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  -v,--verbose          Be verbose
+  -o,--output FILE      Output file
+");
+}
+
+#[test]
+fn test_deprecated() {
+    assert_eq!(parse(&["./argparse_test", "--output=a", "-v"]),
+        (true, "a".to_string(), "".to_string(), "".to_string()));
+    assert_eq!(parse(&["./argparse_test", "--out-file", "a",
+                       "--out-file=b", "--talkative"]),
+        (true, "b".to_string(), "".to_string(),
+         "WARNING: Option --out-file is deprecated: use --output instead\n\
+          WARNING: Option --talkative is deprecated\n".to_string()));
+}
+
+#[test]
+fn test_hidden_option() {
+    let mut debug = false;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut debug)
+        .add_option(&["--debug"], StoreTrue, "Debug internals");
+    ap.hide_option("--debug");
+    let mut buf = Vec::<u8>::new();
+    ap.print_usage("./argparse_test", &mut buf).unwrap();
+    assert_eq!(from_utf8(&buf[..]).unwrap(), "Usage:\n  ./argparse_test\n");
+    let mut buf = Vec::<u8>::new();
+    ap.print_completion(Shell::Bash, "argparse_test", &mut buf).unwrap();
+    assert!(!from_utf8(&buf[..]).unwrap().contains("--debug"));
+    let mut buf = Vec::<u8>::new();
+    ap.print_man_page("argparse_test", "1", &mut buf).unwrap();
+    assert!(!from_utf8(&buf[..]).unwrap().contains("debug"));
+}
+
+#[test]
+#[should_panic(expected="Unknown option --missing")]
+fn test_unknown() {
+    let mut ap = ArgumentParser::new();
+    ap.hide_option("--missing");
+}
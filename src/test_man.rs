@@ -0,0 +1,99 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use super::{Store, StoreTrue, List};
+
+#[test]
+fn test_man_page() {
+    let mut verbose = false;
+    let mut output = String::new();
+    let mut files = Vec::<String>::new();
+    let mut ap = ArgumentParser::new();
+    ap.set_description("Test program. The description is long enough
+        to be wrapped.");
+    ap.refer(&mut verbose)
+        .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+    ap.refer(&mut output)
+        .add_option(&["--output"], Store, "Output file")
+        .envvar("TEST_OUTPUT")
+        .required();
+    ap.refer(&mut files)
+        .add_argument("file", List, "Files to process");
+    let mut buf = Vec::<u8>::new();
+    assert!(ap.print_man_page("argparse-test", "1", &mut buf).is_ok());
+    assert_eq!(".TH ARGPARSE\\-TEST 1\n".to_string()
+        + ".SH NAME\n"
+        + "argparse\\-test \\- Test program\n"
+        + ".SH SYNOPSIS\n"
+        + ".B argparse\\-test\n"
+        + "[\\fIOPTIONS\\fR]\n"
+        + "[\\fIFILE\\fR ...]\n"
+        + ".SH DESCRIPTION\n"
+        + "Test program. The description is long enough to be wrapped.\n"
+        + ".SH OPTIONS\n"
+        + ".TP\n"
+        + "\\fIfile\\fR\n"
+        + "Files to process\n"
+        + ".TP\n"
+        + "\\fB\\-h\\fR, \\fB\\-\\-help\\fR\n"
+        + "Show this help message and exit\n"
+        + ".TP\n"
+        + "\\fB\\-v\\fR, \\fB\\-\\-verbose\\fR\n"
+        + "Be verbose\n"
+        + ".TP\n"
+        + "\\fB\\-\\-output\\fR \\fIOUTPUT\\fR (required)\n"
+        + "Output file\n"
+        + ".SH ENVIRONMENT\n"
+        + ".TP\n"
+        + "\\fBTEST_OUTPUT\\fR (required)\n"
+        + "Output file\n"
+        + "Overridden by \\fB\\-\\-output\\fR.\n"
+        , from_utf8(&buf[..]).unwrap().to_string());
+}
+
+#[test]
+fn test_examples_and_epilog() {
+    let mut output = String::new();
+    let mut ap = ArgumentParser::new();
+    ap.set_description("Test program");
+    ap.refer(&mut output)
+        .add_option(&["--output"], Store, "Output file")
+        .envvar("TEST_OUTPUT");
+    ap.add_example("Write to a file:", "argparse-test --output=x");
+    ap.set_epilog("Report bugs to the issue tracker.
+
+        See also the documentation.");
+    let mut buf = Vec::<u8>::new();
+    assert!(ap.print_man_page("argparse-test", "1", &mut buf).is_ok());
+    assert_eq!(".TH ARGPARSE\\-TEST 1\n".to_string()
+        + ".SH NAME\n"
+        + "argparse\\-test \\- Test program\n"
+        + ".SH SYNOPSIS\n"
+        + ".B argparse\\-test\n"
+        + "[\\fIOPTIONS\\fR]\n"
+        + ".SH DESCRIPTION\n"
+        + "Test program\n"
+        + ".SH OPTIONS\n"
+        + ".TP\n"
+        + "\\fB\\-h\\fR, \\fB\\-\\-help\\fR\n"
+        + "Show this help message and exit\n"
+        + ".TP\n"
+        + "\\fB\\-\\-output\\fR \\fIOUTPUT\\fR\n"
+        + "Output file\n"
+        + ".SH EXAMPLES\n"
+        + ".PP\n"
+        + "Write to a file:\n"
+        + ".RS\n.nf\n"
+        + "argparse-test --output=x\n"
+        + ".fi\n.RE\n"
+        + ".SH ENVIRONMENT\n"
+        + ".TP\n"
+        + "\\fBTEST_OUTPUT\\fR\n"
+        + "Output file\n"
+        + "Overridden by \\fB\\-\\-output\\fR.\n"
+        + ".SH NOTES\n"
+        + "Report bugs to the issue tracker.\n"
+        + ".PP\n"
+        + "See also the documentation.\n"
+        , from_utf8(&buf[..]).unwrap().to_string());
+}
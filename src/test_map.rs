@@ -0,0 +1,81 @@
+use std::collections::{HashMap, BTreeMap};
+use std::path::PathBuf;
+
+use parser::ArgumentParser;
+use super::{CollectMap, ParseMap, DuplicateKeys};
+use test_parser::check_result;
+
+fn defines(args: &[&str], action: CollectMap)
+    -> Result<BTreeMap<String, u32>, String>
+{
+    let mut defines = BTreeMap::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut defines)
+          .add_option(&["-D", "--define"], action, "Define")
+          .metavar("KEY=VALUE");
+        try!(check_result(&ap, args));
+    }
+    return Ok(defines);
+}
+
+fn map(pairs: &[(&str, u32)]) -> Result<BTreeMap<String, u32>, String> {
+    return Ok(pairs.iter().map(|&(k, v)| (k.to_string(), v)).collect());
+}
+
+#[test]
+fn test_collect() {
+    assert_eq!(defines(&["./argparse_test"], CollectMap::new()), map(&[]));
+    assert_eq!(defines(&["./argparse_test", "-D", "a=1", "--define=b=2"],
+                       CollectMap::new()),
+        map(&[("a", 1), ("b", 2)]));
+    assert_eq!(defines(&["./argparse_test", "-Dx==1", "-D", "y=2"],
+                       CollectMap::new()),
+        Err("./argparse_test: Bad value \"x==1\": bad value \"=1\""
+            .to_string()));
+}
+
+#[test]
+fn test_malformed() {
+    assert_eq!(defines(&["./argparse_test", "-D", "a=1", "-D", "b"],
+                       CollectMap::new()),
+        Err("./argparse_test: Bad value \"b\": expected KEY=VALUE"
+            .to_string()));
+    assert_eq!(defines(&["./argparse_test", "-D", "a=x"], CollectMap::new()),
+        Err("./argparse_test: Bad value \"a=x\": bad value \"x\""
+            .to_string()));
+}
+
+#[test]
+fn test_duplicates() {
+    assert_eq!(defines(&["./argparse_test", "-D", "a=1", "-D", "a=2"],
+                       CollectMap::new()),
+        map(&[("a", 2)]));
+    assert_eq!(defines(&["./argparse_test", "-D", "a=1", "-D", "a=2"],
+                       CollectMap::new().duplicates(DuplicateKeys::Error)),
+        Err("./argparse_test: Bad value \"a=2\": duplicate key \"a\""
+            .to_string()));
+}
+
+#[test]
+fn test_separator() {
+    assert_eq!(defines(&["./argparse_test", "-D", "a:1", "-D", "b=c:2"],
+                       CollectMap::new().separator(':')),
+        map(&[("a", 1), ("b=c", 2)]));
+}
+
+#[test]
+fn test_parse_hash_map() {
+    let mut mounts = HashMap::<String, PathBuf>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut mounts)
+          .add_option(&["-v", "--volume"], ParseMap::new().separator(':'),
+            "Volume");
+        ap.parse(vec!["./argparse_test".to_string(),
+            "-v".to_string(), "data:/mnt/data".to_string()],
+            &mut Vec::new(), &mut Vec::new()).unwrap();
+    }
+    assert_eq!(mounts.len(), 1);
+    assert_eq!(mounts["data"], PathBuf::from("/mnt/data"));
+}
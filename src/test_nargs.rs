@@ -0,0 +1,159 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use super::{List, Collect, StoreTrue};
+use test_parser::{check_ok, check_result};
+
+fn point(args: &[&str]) -> Result<(Vec<i32>, bool), String> {
+    let mut point = vec!();
+    let mut verbose = false;
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut point)
+          .add_option(&["-p", "--point"], List, "Point")
+          .nargs(2);
+        ap.refer(&mut verbose)
+          .add_option(&["-v", "--verbose"], StoreTrue, "Verbose");
+        try!(check_result(&ap, args));
+    }
+    return Ok((point, verbose));
+}
+
+#[test]
+fn test_exact() {
+    assert_eq!(point(&["./argparse_test"]), Ok((vec!(), false)));
+    assert_eq!(point(&["./argparse_test", "--point", "1", "2"]),
+        Ok((vec!(1, 2), false)));
+    assert_eq!(point(&["./argparse_test", "-p", "1", "2", "-v"]),
+        Ok((vec!(1, 2), true)));
+    assert_eq!(point(&["./argparse_test", "--point=1", "2"]),
+        Ok((vec!(1, 2), false)));
+    assert_eq!(point(&["./argparse_test", "-p1", "2"]),
+        Ok((vec!(1, 2), false)));
+}
+
+#[test]
+fn test_exact_errors() {
+    assert_eq!(point(&["./argparse_test", "--point", "1"]),
+        Err("./argparse_test: --point requires 2 values, got 1".to_string()));
+    assert_eq!(point(&["./argparse_test", "--point", "1", "-v"]),
+        Err("./argparse_test: --point requires 2 values, got 1".to_string()));
+    assert_eq!(point(&["./argparse_test", "-p"]),
+        Err("./argparse_test: -p requires 2 values, got 0".to_string()));
+    assert_eq!(point(&["./argparse_test", "-p", "1", "2", "3"]),
+        Err("./argparse_test: Unexpected argument 3".to_string()));
+}
+
+fn range(args: &[&str]) -> Vec<String> {
+    let mut files = vec!();
+    let mut rest = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut files)
+          .add_option(&["-f", "--file"], Collect, "File")
+          .nargs(1..=3);
+        ap.refer(&mut rest)
+          .add_argument("rest", List, "Rest");
+        check_ok(&ap, args);
+    }
+    files.extend(rest.into_iter().map(|x: String| format!("+{}", x)));
+    return files;
+}
+
+#[test]
+fn test_range() {
+    assert_eq!(range(&["./argparse_test", "-f", "a"]), vec!("a"));
+    assert_eq!(range(&["./argparse_test", "-f", "a", "b", "c", "d"]),
+        vec!("a", "b", "c", "+d"));
+    assert_eq!(range(&["./argparse_test", "-f", "a", "-f", "b", "c"]),
+        vec!("a", "b", "c"));
+    assert_eq!(range(&["./argparse_test", "-f", "a", "--", "b"]),
+        vec!("a", "+b"));
+}
+
+fn coords(args: &[&str]) -> Result<Vec<f32>, String> {
+    let mut coords = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut coords)
+          .add_argument("coords", List, "Coordinates")
+          .nargs(2..);
+        try!(check_result(&ap, args));
+    }
+    return Ok(coords);
+}
+
+#[test]
+fn test_argument() {
+    assert_eq!(coords(&["./argparse_test"]),
+        Err("./argparse_test: coords requires at least 2 values, got 0"
+            .to_string()));
+    assert_eq!(coords(&["./argparse_test", "1", "2", "3"]),
+        Ok(vec!(1., 2., 3.)));
+    assert_eq!(coords(&["./argparse_test", "1"]),
+        Err("./argparse_test: coords requires at least 2 values, got 1"
+            .to_string()));
+}
+
+#[test]
+fn test_optional_argument() {
+    let mut coords: Vec<f32> = vec!();
+    let mut required: Vec<f32> = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut coords)
+          .add_argument("coords", List, "Coordinates")
+          .nargs(0..=2);
+        assert_eq!(check_result(&ap, &["./argparse_test"]), Ok(()));
+    }
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut required)
+          .add_argument("coords", List, "Coordinates")
+          .nargs(2)
+          .required();
+        assert_eq!(check_result(&ap, &["./argparse_test"]),
+            Err("./argparse_test: Argument coords is required".to_string()));
+    }
+}
+
+#[test]
+fn test_help() {
+    let mut point: Vec<f32> = vec!();
+    let mut size: Vec<u32> = vec!();
+    let mut files: Vec<String> = vec!();
+    let mut tags: Vec<String> = vec!();
+    let mut coords: Vec<f32> = vec!();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut point)
+      .add_option(&["--point"], List, "Point")
+      .nargs(2).metavar("X Y");
+    ap.refer(&mut size)
+      .add_option(&["--size"], List, "Size")
+      .nargs(2);
+    ap.refer(&mut files)
+      .add_option(&["--file"], Collect, "Files")
+      .nargs(1..=2);
+    ap.refer(&mut tags)
+      .add_option(&["--tag"], Collect, "Tags")
+      .nargs(1..);
+    ap.refer(&mut coords)
+      .add_argument("coords", List, "Coordinates")
+      .nargs(2);
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    assert_eq!(from_utf8(&buf[..]).unwrap(), "Usage:
+  ./argparse_test [OPTIONS] COORDS COORDS
+
+
+Positional arguments:
+  coords                Coordinates
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  --point X Y           Point
+  --size SIZE SIZE      Size
+  --file FILE [FILE]    Files
+  --tag TAG [TAG...]    Tags
+");
+}
@@ -0,0 +1,85 @@
+use std::env;
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use completion::Shell;
+use super::Store;
+
+fn parse(args: &[&str]) -> (bool, String, String) {
+    let mut color = true;
+    let mut name = String::new();
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut color)
+            .add_negatable_option(&["-c", "--color"], "Use colors")
+            .envvar("TEST_NEGATABLE_COLOR");
+        ap.refer(&mut name)
+            .add_option(&["--name"], Store, "Name");
+        let args = args.iter().map(|x| x.to_string()).collect();
+        ap.parse(args, &mut stdout, &mut stderr).ok();
+    }
+    return (color,
+            from_utf8(&stdout[..]).unwrap().to_string(),
+            from_utf8(&stderr[..]).unwrap().to_string());
+}
+
+#[test]
+fn test_negation() {
+    assert_eq!(parse(&["./argparse_test"]).0, true);
+    assert_eq!(parse(&["./argparse_test", "--no-color"]).0, false);
+    assert_eq!(parse(&["./argparse_test", "--no-color", "-c"]).0, true);
+    assert_eq!(parse(&["./argparse_test", "--color", "--no-color"]).0,
+        false);
+}
+
+#[test]
+fn test_help() {
+    assert_eq!(parse(&["./argparse_test", "--help"]).1, "Usage:
+  ./argparse_test [OPTIONS]
+
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  -c,--[no-]color       Use colors [env: TEST_NEGATABLE_COLOR]
+  --name NAME           Name
+");
+}
+
+#[test]
+fn test_envvar() {
+    env::set_var("TEST_NEGATABLE_COLOR", "OFF");
+    let off = parse(&["./argparse_test"]);
+    env::set_var("TEST_NEGATABLE_COLOR", "1");
+    let on = parse(&["./argparse_test"]);
+    let overridden = parse(&["./argparse_test", "--no-color"]);
+    env::set_var("TEST_NEGATABLE_COLOR", "maybe");
+    let bad = parse(&["./argparse_test"]);
+    env::remove_var("TEST_NEGATABLE_COLOR");
+    assert_eq!(off.0, false);
+    assert_eq!(on.0, true);
+    assert_eq!(overridden.0, false);
+    assert_eq!(bad, (true, "".to_string(),
+        "WARNING: Environment variable TEST_NEGATABLE_COLOR: \
+         Bad value maybe\n".to_string()));
+}
+
+#[test]
+fn test_completion() {
+    let mut color = true;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut color)
+        .add_negatable_option(&["-c", "--color"], "Use colors");
+    let complete = |shell| {
+        let mut buf = Vec::<u8>::new();
+        ap.print_completion(shell, "prog", &mut buf).unwrap();
+        return from_utf8(&buf[..]).unwrap().to_string();
+    };
+    assert!(complete(Shell::Bash).contains(
+        "compgen -W '-h --help -c --color --no-color' -- \"$cur\""));
+    assert!(complete(Shell::Zsh).contains(
+        "'(-c --color --no-color)'{-c,--color,--no-color}'[Use colors]'"));
+    assert!(complete(Shell::Fish).contains(
+        "complete -c prog -s c -l color -l no-color -d 'Use colors'\n"));
+}
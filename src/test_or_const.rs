@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use super::{StoreOrConst, ParseOrConst, StoreTrue, Store};
+use test_parser::{check_ok, check_err};
+
+
+fn color(args: &[&str]) -> (String, bool, String) {
+    let mut color = "auto".to_string();
+    let mut verbose = false;
+    let mut file = String::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut color)
+          .add_option(&["-c", "--color"], StoreOrConst("always".to_string()),
+            "When to use colors")
+          .metavar("WHEN");
+        ap.refer(&mut verbose)
+          .add_option(&["-v", "--verbose"], StoreTrue, "Verbose");
+        ap.refer(&mut file)
+          .add_argument("file", Store, "File");
+        check_ok(&ap, args);
+    }
+    return (color, verbose, file);
+}
+
+fn result(color: &str, verbose: bool, file: &str) -> (String, bool, String) {
+    return (color.to_string(), verbose, file.to_string());
+}
+
+#[test]
+fn test_no_option() {
+    assert_eq!(color(&["./argparse_test"]), result("auto", false, ""));
+}
+
+#[test]
+fn test_const() {
+    assert_eq!(color(&["./argparse_test", "--color"]),
+        result("always", false, ""));
+    assert_eq!(color(&["./argparse_test", "-c"]),
+        result("always", false, ""));
+    assert_eq!(color(&["./argparse_test", "--color", "-v"]),
+        result("always", true, ""));
+    assert_eq!(color(&["./argparse_test", "-vc"]),
+        result("always", true, ""));
+    assert_eq!(color(&["./argparse_test", "--color", "--", "x"]),
+        result("always", false, "x"));
+}
+
+#[test]
+fn test_value() {
+    assert_eq!(color(&["./argparse_test", "--color=never"]),
+        result("never", false, ""));
+    assert_eq!(color(&["./argparse_test", "--color", "never", "x"]),
+        result("never", false, "x"));
+    assert_eq!(color(&["./argparse_test", "-cnever"]),
+        result("never", false, ""));
+    assert_eq!(color(&["./argparse_test", "-vc", "never"]),
+        result("never", true, ""));
+    assert_eq!(color(&["./argparse_test", "x", "-c", "-"]),
+        result("-", false, "x"));
+}
+
+#[test]
+fn test_help() {
+    let mut color = "auto".to_string();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut color)
+      .add_option(&["-c", "--color"], StoreOrConst("always".to_string()),
+        "When to use colors")
+      .metavar("WHEN");
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    assert_eq!(from_utf8(&buf[..]).unwrap(), "Usage:
+  ./argparse_test [OPTIONS]
+
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  -c,--color [WHEN]     When to use colors
+");
+}
+
+fn level(args: &[&str]) -> u32 {
+    let mut level = 0;
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut level)
+          .add_option(&["-O", "--optimize"], StoreOrConst(2),
+            "Optimization level");
+        check_ok(&ap, args);
+    }
+    return level;
+}
+
+#[test]
+fn test_int() {
+    assert_eq!(level(&["./argparse_test"]), 0);
+    assert_eq!(level(&["./argparse_test", "-O"]), 2);
+    assert_eq!(level(&["./argparse_test", "-O3"]), 3);
+    assert_eq!(level(&["./argparse_test", "--optimize", "1"]), 1);
+}
+
+#[test]
+#[should_panic]
+fn test_bad_value() {
+    level(&["./argparse_test", "-O", "x"]);
+}
+
+#[test]
+fn test_bad_value_error() {
+    let mut level = 0u32;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut level)
+      .add_option(&["-O", "--optimize"], StoreOrConst(2),
+        "Optimization level");
+    check_err(&ap, &["./argparse_test", "--optimize=x"]);
+}
+
+#[test]
+fn test_parse() {
+    let mut path = PathBuf::from("default.log");
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut path)
+          .add_option(&["--log"], ParseOrConst(PathBuf::from("app.log")),
+            "Log file");
+        check_ok(&ap, &["./argparse_test", "--log"]);
+    }
+    assert_eq!(path, PathBuf::from("app.log"));
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut path)
+          .add_option(&["--log"], ParseOrConst(PathBuf::from("app.log")),
+            "Log file");
+        check_ok(&ap, &["./argparse_test", "--log", "/tmp/x.log"]);
+    }
+    assert_eq!(path, PathBuf::from("/tmp/x.log"));
+}
@@ -0,0 +1,75 @@
+use std::env;
+
+use parser::ArgumentParser;
+use config::Config;
+use origin::{Source, Origin, Provenance};
+use super::{Store, StoreTrue, Collect};
+
+fn parse(config: &Config, args: &[&str]) -> Provenance {
+    let mut verbose = false;
+    let mut port = 80u16;
+    let mut host = "localhost".to_string();
+    let mut files = Vec::<String>::new();
+    let mut ap = ArgumentParser::new();
+    ap.set_config(config);
+    ap.refer(&mut verbose)
+        .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+    ap.refer(&mut port)
+        .add_option(&["-p", "--port"], Store, "Port")
+        .envvar("TEST_ORIGIN_PORT");
+    ap.refer(&mut host)
+        .add_option(&["--host"], Store, "Host")
+        .config_key("server.host", Store);
+    ap.refer(&mut files)
+        .add_argument("files", Collect, "Files");
+    let args = args.iter().map(|x| x.to_string()).collect();
+    return ap.try_parse_provenance(args, &mut Vec::new(), &mut Vec::new())
+        .unwrap();
+}
+
+fn origin(source: Source, name: &str, values: &[&str]) -> Origin {
+    return Origin {
+        source: source,
+        name: name.to_string(),
+        values: values.iter().map(|x| x.to_string()).collect(),
+    };
+}
+
+#[test]
+fn test_defaults() {
+    let prov = parse(&Config::new(), &["./argparse_test"]);
+    assert_eq!(prov.iter().count(), 4);
+    let port = prov.get("--port").unwrap();
+    assert_eq!(port.names, vec!["-p", "--port", "TEST_ORIGIN_PORT"]);
+    assert_eq!(port.origin, origin(Source::Default, "", &[]));
+    assert!(port.overridden.is_empty());
+}
+
+#[test]
+fn test_command_line() {
+    let prov = parse(&Config::new(),
+        &["./argparse_test", "-v", "a", "--port=8080", "b"]);
+    assert_eq!(prov.get("--verbose").unwrap().origin,
+        origin(Source::CommandLine, "-v", &[]));
+    assert_eq!(prov.get("-p").unwrap().origin,
+        origin(Source::CommandLine, "--port", &["8080"]));
+    assert_eq!(prov.get("files").unwrap().origin,
+        origin(Source::CommandLine, "files", &["a", "b"]));
+}
+
+#[test]
+fn test_overridden() {
+    let mut config = Config::new();
+    config.set("server.host", "example.com");
+    env::set_var("TEST_ORIGIN_PORT", "8000");
+    let prov = parse(&config, &["./argparse_test", "-p", "1", "-p2"]);
+    env::remove_var("TEST_ORIGIN_PORT");
+    let port = prov.get("TEST_ORIGIN_PORT").unwrap();
+    assert_eq!(port.origin, origin(Source::CommandLine, "-p", &["2"]));
+    assert_eq!(port.overridden, vec![
+        origin(Source::EnvVar, "TEST_ORIGIN_PORT", &["8000"]),
+        origin(Source::CommandLine, "-p", &["1"]),
+    ]);
+    assert_eq!(prov.get("server.host").unwrap().origin,
+        origin(Source::Config, "server.host", &["example.com"]));
+}
@@ -0,0 +1,78 @@
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use parser::ArgumentParser;
+use error::ParseError;
+use super::{Parse, ParseList, Store};
+
+fn os(bytes: &[u8]) -> OsString {
+    return OsStr::from_bytes(bytes).to_os_string();
+}
+
+fn parse_os(ap: &ArgumentParser, args: &[&[u8]]) -> Result<(), ParseError> {
+    let owned_args = args.iter().map(|x| os(x)).collect();
+    return ap.try_parse_os(owned_args, &mut Vec::new(), &mut Vec::new());
+}
+
+#[test]
+fn test_path() {
+    let mut path = PathBuf::new();
+    let mut raw = OsString::new();
+    let mut files = Vec::<PathBuf>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut path)
+            .add_option(&["-p", "--path"], Parse, "Path");
+        ap.refer(&mut raw)
+            .add_option(&["-r", "--raw"], Parse, "Raw value");
+        ap.refer(&mut files)
+            .add_argument("files", ParseList, "Files");
+        parse_os(&ap, &[b"./argparse_test", b"--path=a\xffb", b"-r\xfe",
+                        b"x\xff", b"y"]).unwrap();
+    }
+    assert_eq!(path, PathBuf::from(os(b"a\xffb")));
+    assert_eq!(raw, os(b"\xfe"));
+    assert_eq!(files, vec![PathBuf::from(os(b"x\xff")),
+                           PathBuf::from("y")]);
+}
+
+#[test]
+fn test_invalid_utf8() {
+    let mut name = String::new();
+    let mut count = 0u32;
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut name)
+        .add_option(&["--name"], Store, "Name");
+    ap.refer(&mut count)
+        .add_argument("count", Parse, "Count");
+    assert_eq!(parse_os(&ap, &[b"./argparse_test", b"--name", b"a\xff"]),
+        Err(ParseError::BadValue {
+            name: "--name".to_string(),
+            value: "a\u{fffd}".to_string(),
+            metavar: "NAME".to_string(),
+            reason: Some("invalid utf-8".to_string()),
+            suggestion: None,
+        }));
+    assert_eq!(parse_os(&ap, &[b"./argparse_test", b"1\xff"])
+        .unwrap_err().to_string(),
+        "Bad value \"1\u{fffd}\": invalid utf-8");
+    assert_eq!(parse_os(&ap, &[b"./argparse_test", b"--n\xffme=x"]),
+        Err(ParseError::UnknownOption {
+            option: "--n\u{fffd}me=x".to_string(),
+            suggestion: Some("--name".to_string()),
+        }));
+}
+
+#[test]
+fn test_utf8() {
+    let mut name = String::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut name)
+            .add_option(&["-n", "--name"], Store, "Name");
+        parse_os(&ap, &[b"./argparse_test", "-nпривіт".as_bytes()])
+            .unwrap();
+    }
+    assert_eq!(name, "привіт");
+}
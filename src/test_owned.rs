@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::str::from_utf8;
+
+use owned::{OwnedParser, Handle};
+use super::{Store, StoreTrue, Collect, List, Parse, CollectMap};
+
+struct Cli {
+    parser: OwnedParser,
+    verbose: Handle<bool>,
+    name: Handle<String>,
+    items: Handle<Vec<u32>>,
+}
+
+fn cli() -> Cli {
+    let mut parser = OwnedParser::new();
+    parser.set_description("Test program");
+    let verbose = parser.add_var(false)
+        .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose")
+        .handle();
+    let name = parser.add_var("World".to_string())
+        .add_option(&["--name"], Store, "Name for the greeting")
+        .envvar("TEST_OWNED_NAME")
+        .handle();
+    let items = parser.add_var(Vec::new())
+        .add_argument("items", Collect, "Items")
+        .handle();
+    return Cli { parser: parser, verbose: verbose, name: name, items: items };
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    return args.iter().map(|x| x.to_string()).collect();
+}
+
+#[test]
+fn test_parse() {
+    let cli = cli();
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    let values = cli.parser.parse(args(&["./argparse_test"]),
+        &mut stdout, &mut stderr).unwrap();
+    assert_eq!(*values.get(&cli.verbose), false);
+    assert_eq!(values.get(&cli.name), "World");
+    assert_eq!(*values.get(&cli.items), Vec::<u32>::new());
+
+    let mut values = cli.parser.parse(
+        args(&["./argparse_test", "-v", "--name=Bob", "1", "2"]),
+        &mut stdout, &mut stderr).unwrap();
+    assert_eq!(*values.get(&cli.verbose), true);
+    assert_eq!(values.take(&cli.name), "Bob".to_string());
+    assert_eq!(values.take(&cli.items), vec![1, 2]);
+}
+
+#[test]
+fn test_envvar() {
+    let cli = cli();
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    env::set_var("TEST_OWNED_NAME", "Alice");
+    let values = cli.parser.parse(args(&["./argparse_test"]),
+        &mut stdout, &mut stderr).unwrap();
+    env::remove_var("TEST_OWNED_NAME");
+    assert_eq!(values.get(&cli.name), "Alice");
+}
+
+#[test]
+fn test_error() {
+    let cli = cli();
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    assert_eq!(cli.parser.parse(args(&["./argparse_test", "x"]),
+        &mut stdout, &mut stderr).err(), Some(2));
+    assert_eq!(cli.parser.parse(args(&["./argparse_test", "--help"]),
+        &mut stdout, &mut stderr).err(), Some(0));
+}
+
+#[test]
+fn test_help() {
+    let cli = cli();
+    let mut buf = Vec::<u8>::new();
+    assert!(cli.parser.print_help("./argparse_test", &mut buf).is_ok());
+    assert_eq!("Usage:\n".to_string()
+        + "  ./argparse_test [OPTIONS] [ITEMS ...]\n"
+        + "\n"
+        + "Test program\n"
+        + "\n"
+        + "Positional arguments:\n"
+        + "  items                 Items\n"
+        + "\n"
+        + "Optional arguments:\n"
+        + "  -h,--help             Show this help message and exit\n"
+        + "  -v,--verbose          Be verbose\n"
+        + "  --name NAME           Name for the greeting \
+            [env: TEST_OWNED_NAME]\n"
+        , from_utf8(&buf[..]).unwrap().to_string());
+}
+
+#[test]
+fn test_negatable() {
+    let mut ap = OwnedParser::new();
+    let color = ap.add_var(true)
+        .add_negatable_option(&["--color"], "Use colors")
+        .handle();
+    let values = ap.parse(args(&["./argparse_test", "--no-color"]),
+        &mut Vec::new(), &mut Vec::new()).unwrap();
+    assert_eq!(*values.get(&color), false);
+}
+
+#[test]
+fn test_nargs() {
+    let mut ap = OwnedParser::new();
+    let point = ap.add_var(Vec::<u32>::new())
+        .add_option(&["--point"], List, "Point")
+        .nargs(2)
+        .handle();
+    let values = ap.parse(args(&["./argparse_test", "--point", "1", "2"]),
+        &mut Vec::new(), &mut Vec::new()).unwrap();
+    assert_eq!(*values.get(&point), vec!(1, 2));
+}
+
+#[test]
+fn test_tuple() {
+    let mut ap = OwnedParser::new();
+    let size = ap.add_var((0u32, 0u32))
+        .add_option(&["--size"], Parse, "Size")
+        .handle();
+    let values = ap.parse(args(&["./argparse_test", "--size", "3", "4"]),
+        &mut Vec::new(), &mut Vec::new()).unwrap();
+    assert_eq!(*values.get(&size), (3, 4));
+}
+
+#[test]
+fn test_map() {
+    let mut ap = OwnedParser::new();
+    let labels = ap.add_var(BTreeMap::<String, String>::new())
+        .add_option(&["--label"], CollectMap::new(), "Label")
+        .handle();
+    let values = ap.parse(args(&["./argparse_test", "--label", "k=v"]),
+        &mut Vec::new(), &mut Vec::new()).unwrap();
+    assert_eq!(values.get(&labels).get("k").map(|x| &x[..]), Some("v"));
+}
+
+#[test]
+fn test_delimiter() {
+    let mut ap = OwnedParser::new();
+    let tags = ap.add_var(Vec::<String>::new())
+        .add_option(&["--tags"], Collect, "Tags")
+        .delimiter(',')
+        .handle();
+    let values = ap.parse(args(&["./argparse_test", "--tags", "a,b"]),
+        &mut Vec::new(), &mut Vec::new()).unwrap();
+    assert_eq!(*values.get(&tags), vec!("a", "b"));
+}
@@ -47,6 +47,24 @@ pub fn check_err(ap: &ArgumentParser, args: &[&str]) {
     }
 }
 
+/// Parse the arguments, returning the last line of stderr on error
+pub fn check_result(ap: &ArgumentParser, args: &[&str])
+    -> Result<(), String>
+{
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    let mut owned_args = Vec::new();
+    for x in args.iter() {
+        owned_args.push(x.to_string());
+    }
+    let res = ap.parse(owned_args, &mut stdout, &mut stderr);
+    match res {
+        Ok(()) => return Ok(()),
+        Err(_) => return Err(String::from_utf8(stderr).unwrap()
+            .lines().last().unwrap().to_string()),
+    }
+}
+
 #[test]
 fn test_no_arg() {
     let ap = ArgumentParser::new();
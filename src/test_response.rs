@@ -0,0 +1,96 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process;
+
+use parser::ArgumentParser;
+use error::ParseError;
+use response::ResponseFiles;
+use super::{Store, Collect};
+
+fn write_file(name: &str, data: &str) -> String {
+    let mut dir = env::temp_dir();
+    dir.push(format!("argparse-test-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path: PathBuf = dir.join(name);
+    File::create(&path).unwrap().write_all(data.as_bytes()).unwrap();
+    return path.to_str().unwrap().to_string();
+}
+
+fn parse(mode: ResponseFiles, args: &[&str])
+    -> Result<(String, Vec<String>), ParseError>
+{
+    let mut name = String::new();
+    let mut items = Vec::<String>::new();
+    let res = {
+        let mut ap = ArgumentParser::new();
+        ap.response_files(mode);
+        ap.refer(&mut name)
+            .add_option(&["--name"], Store, "Name");
+        ap.refer(&mut items)
+            .add_argument("items", Collect, "Items");
+        let args = args.iter().map(|x| x.to_string()).collect();
+        ap.try_parse(args, &mut Vec::new(), &mut Vec::new())
+    };
+    return res.map(|()| (name, items));
+}
+
+#[test]
+fn test_disabled() {
+    let path = write_file("disabled.args", "--name\nx\n");
+    let arg = format!("@{}", path);
+    assert_eq!(parse(ResponseFiles::Disabled, &["./argparse_test", &arg]),
+        Ok(("".to_string(), vec![arg.clone()])));
+}
+
+#[test]
+fn test_lines() {
+    let path = write_file("lines.args", "--name\r\nJohn Doe\n\nitem 1\n");
+    let arg = format!("@{}", path);
+    assert_eq!(parse(ResponseFiles::Lines,
+                     &["./argparse_test", "a", &arg, "b", "--", &arg]),
+        Ok(("John Doe".to_string(), vec!["a".to_string(),
+            "item 1".to_string(), "b".to_string(), arg.clone()])));
+}
+
+#[test]
+fn test_shell() {
+    let nested = write_file("nested.args", "c 'd e'");
+    let path = write_file("shell.args", &format!(
+        "# comment\n--name \"John \\\"Doe\\\"\" a\\ b\n@{} 'multi\nline'\n",
+        nested));
+    assert_eq!(parse(ResponseFiles::Shell,
+                     &["./argparse_test", &format!("@{}", path)]),
+        Ok(("John \"Doe\"".to_string(), vec!["a b".to_string(),
+            "c".to_string(), "d e".to_string(),
+            "multi\nline".to_string()])));
+}
+
+#[test]
+fn test_errors() {
+    let cycle = write_file("cycle.args", "x\n");
+    let cycle = write_file("cycle.args",
+        &format!("x\n@{}\n", cycle));
+    assert_eq!(parse(ResponseFiles::Lines,
+                     &["./argparse_test", &format!("@{}", cycle)]),
+        Err(ParseError::ResponseFile {
+            path: cycle.clone(),
+            line: Some(2),
+            message: format!("recursive inclusion of {:?}", cycle),
+        }));
+    let quote = write_file("quote.args", "a\n'b\nc\n");
+    assert_eq!(parse(ResponseFiles::Shell,
+                     &["./argparse_test", &format!("@{}", quote)])
+        .unwrap_err().to_string(),
+        format!("Response file {}:2: unterminated quote", quote));
+    let missing = format!("{}.missing", quote);
+    match parse(ResponseFiles::Shell,
+                &["./argparse_test", &format!("@{}", missing)])
+    {
+        Err(ParseError::ResponseFile { ref path, line: None, .. }) => {
+            assert_eq!(path, &missing);
+        }
+        res => panic!("Unexpected result {:?}", res),
+    }
+}
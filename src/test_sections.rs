@@ -0,0 +1,117 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use help::dedent;
+use super::StoreTrue;
+
+fn parser<'a>(verbose: &'a mut bool) -> ArgumentParser<'a> {
+    let mut ap = ArgumentParser::new();
+    ap.set_description("Test program");
+    ap.refer(verbose)
+        .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+    ap.add_section("Exit status", "Zero on success, two on bad
+        arguments.
+
+        One on other errors.");
+    ap.add_verbatim_section("Config", "
+        [server]
+          port = 8080
+
+        host = \"localhost\"
+        ");
+    ap.add_example("Run verbosely:", "./argparse_test -v  # note spaces");
+    ap.add_example("", "./argparse_test");
+    ap.set_epilog("See also the documentation at
+        https://example.com");
+    return ap;
+}
+
+#[test]
+fn test_help() {
+    let mut verbose = false;
+    let ap = parser(&mut verbose);
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    assert_eq!(from_utf8(&buf[..]).unwrap(), "Usage:
+  ./argparse_test [OPTIONS]
+
+Test program
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  -v,--verbose          Be verbose
+
+Exit status:
+  Zero on success, two on bad arguments.
+
+  One on other errors.
+
+Config:
+  [server]
+    port = 8080
+
+  host = \"localhost\"
+
+Examples:
+  Run verbosely:
+    ./argparse_test -v  # note spaces
+    ./argparse_test
+
+See also the documentation at https://example.com
+");
+}
+
+#[test]
+fn test_man_page() {
+    let mut verbose = false;
+    let ap = parser(&mut verbose);
+    let mut buf = Vec::<u8>::new();
+    ap.print_man_page("argparse-test", "1", &mut buf).unwrap();
+    let man = from_utf8(&buf[..]).unwrap();
+    assert!(man.contains(".SH EXIT STATUS
+Zero on success, two on bad arguments.
+.PP
+One on other errors.
+.SH CONFIG
+.nf
+[server]
+  port = 8080
+
+host = \"localhost\"
+.fi
+.SH EXAMPLES
+.PP
+Run verbosely:
+.RS
+.nf
+\\&./argparse_test -v  # note spaces
+.fi
+.RE
+.PP
+.RS
+.nf
+\\&./argparse_test
+.fi
+.RE
+.SH NOTES
+See also the documentation at https://example.com
+"), "{}", man);
+}
+
+#[test]
+fn test_dedent() {
+    assert_eq!(dedent("\n\n    a\n      b\n\n    c  \n  \n"),
+        vec!["a", "  b", "", "c"]);
+    assert_eq!(dedent(""), Vec::<&str>::new());
+}
+
+#[test]
+fn test_dedent_unicode_whitespace() {
+    assert_eq!(dedent("\u{a0}x\n y"), vec!["\u{a0}x", " y"]);
+    let mut ap = ArgumentParser::new();
+    ap.add_verbatim_section("Cfg", "\u{a0}x\n y");
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    assert!(from_utf8(&buf[..]).unwrap()
+        .ends_with("Cfg:\n  \u{a0}x\n   y\n"));
+}
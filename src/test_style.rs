@@ -0,0 +1,125 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use style::{ColorChoice, Theme};
+use super::Store;
+
+fn parse(color: Option<ColorChoice>, args: &[&str]) -> (String, String) {
+    let mut val = 0;
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        match color {
+            Some(color) => ap.set_color(color),
+            None => {}
+        }
+        ap.add_color_option();
+        ap.refer(&mut val)
+            .add_option(&["-s", "--set"], Store, "Set value");
+        let args = args.iter().map(|x| x.to_string()).collect();
+        ap.parse(args, &mut stdout, &mut stderr).ok();
+    }
+    return (from_utf8(&stdout[..]).unwrap().to_string(),
+            from_utf8(&stderr[..]).unwrap().to_string());
+}
+
+const PLAIN_HELP: &'static str = "\
+Usage:
+  ./argparse_test [OPTIONS]
+
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  --color WHEN          When to use colors: auto, always or never [possible
+                        values: auto, always, never]
+  -s,--set SET          Set value
+";
+
+#[test]
+fn test_plain() {
+    assert_eq!(parse(None, &["./argparse_test", "--help"]),
+        (PLAIN_HELP.to_string(), "".to_string()));
+    assert_eq!(parse(Some(ColorChoice::Always),
+                     &["./argparse_test", "--color=never", "--help"]),
+        (PLAIN_HELP.to_string(), "".to_string()));
+}
+
+#[test]
+fn test_colored_help() {
+    assert_eq!(parse(None, &["./argparse_test", "--color", "always", "-h"]).0,
+        "\x1b[1;4mUsage:\x1b[0m
+  ./argparse_test [OPTIONS]
+
+
+\x1b[1;4mOptional arguments:\x1b[0m
+  \x1b[1m-h\x1b[0m,\x1b[1m--help\x1b[0m             \
+Show this help message and exit
+  \x1b[1m--color\x1b[0m \x1b[3mWHEN\x1b[0m          \
+When to use colors: auto, always or never [possible
+                        values: auto, always, never]
+  \x1b[1m-s\x1b[0m,\x1b[1m--set\x1b[0m \x1b[3mSET\x1b[0m          \
+Set value
+");
+}
+
+#[test]
+fn test_colored_error() {
+    assert_eq!(parse(Some(ColorChoice::Always), &["./argparse_test", "-x"]).1,
+        "\x1b[1;4mUsage:\x1b[0m
+  ./argparse_test [OPTIONS]
+\x1b[1;31m./argparse_test:\x1b[0m Unknown short option \"x\"
+");
+}
+
+#[test]
+fn test_bad_choice() {
+    assert_eq!(parse(None, &["./argparse_test", "--color=yes"]).1,
+        "Usage:
+  ./argparse_test [OPTIONS]
+./argparse_test: Bad value \"yes\": expected auto, always or never
+");
+}
+
+#[test]
+fn test_theme() {
+    let mut ap = ArgumentParser::new();
+    ap.set_color(ColorChoice::Always);
+    ap.set_theme(Theme {
+        header: "".to_string(),
+        option: "32".to_string(),
+        metavar: "".to_string(),
+        error: "".to_string(),
+    });
+    let mut stdout = Vec::<u8>::new();
+    ap.parse(vec!["./argparse_test".to_string(), "-h".to_string()],
+        &mut stdout, &mut Vec::new()).ok();
+    assert_eq!(from_utf8(&stdout[..]).unwrap(),
+        "Usage:
+  ./argparse_test
+
+
+Optional arguments:
+  \x1b[32m-h\x1b[0m,\x1b[32m--help\x1b[0m             \
+Show this help message and exit
+");
+}
+
+#[test]
+fn test_choice() {
+    assert!(ColorChoice::Always.enabled(false));
+    assert!(!ColorChoice::Never.enabled(true));
+    assert!(!ColorChoice::Auto.enabled(false));
+}
+
+#[test]
+fn test_option_not_kept() {
+    let mut ap = ArgumentParser::new();
+    ap.add_color_option();
+    ap.parse(vec!["./argparse_test".to_string(), "--color=always".to_string()],
+        &mut Vec::new(), &mut Vec::new()).unwrap();
+    let mut stdout = Vec::<u8>::new();
+    ap.parse(vec!["./argparse_test".to_string(), "-h".to_string()],
+        &mut stdout, &mut Vec::new()).ok();
+    assert!(!from_utf8(&stdout[..]).unwrap().contains("\x1b["));
+}
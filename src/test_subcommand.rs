@@ -0,0 +1,136 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use super::{Store, StoreTrue};
+use test_parser::{check_ok, check_err};
+
+fn parse_cmd(args: &[&str]) -> (bool, String, String, String) {
+    let mut verbose = false;
+    let mut command = String::new();
+    let mut output = String::new();
+    let mut input = String::new();
+    {
+        let mut play = ArgumentParser::new();
+        play.refer(&mut output)
+            .add_option(&["--output"], Store, "Output sink");
+        let mut record = ArgumentParser::new();
+        record.refer(&mut input)
+            .add_argument("input", Store, "Input source");
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut verbose)
+            .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+        ap.refer(&mut command)
+            .add_subcommand("play", "Play a sound", play)
+            .add_subcommand("record", "Record a sound", record);
+        check_ok(&ap, args);
+    }
+    return (verbose, command, output, input);
+}
+
+#[test]
+fn test_no_command() {
+    assert_eq!(parse_cmd(&["./argparse_test"]),
+        (false, "".to_string(), "".to_string(), "".to_string()));
+}
+
+#[test]
+fn test_commands() {
+    assert_eq!(parse_cmd(&["./argparse_test", "play", "--output=x"]),
+        (false, "play".to_string(), "x".to_string(), "".to_string()));
+    assert_eq!(parse_cmd(&["./argparse_test", "-v", "record", "mic"]),
+        (true, "record".to_string(), "".to_string(), "mic".to_string()));
+}
+
+#[test]
+#[should_panic]
+fn test_option_after_command() {
+    parse_cmd(&["./argparse_test", "play", "-v"]);
+}
+
+#[test]
+#[should_panic]
+fn test_unknown_command() {
+    parse_cmd(&["./argparse_test", "stop"]);
+}
+
+#[test]
+fn test_required() {
+    let mut command = String::new();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut command)
+        .add_subcommand("play", "Play a sound", ArgumentParser::new())
+        .required();
+    check_err(&ap, &["./argparse_test"]);
+    check_ok(&ap, &["./argparse_test", "play"]);
+}
+
+#[test]
+fn test_error_path() {
+    let mut command = String::new();
+    let mut output = String::new();
+    let mut play = ArgumentParser::new();
+    play.refer(&mut output)
+        .add_option(&["--output"], Store, "Output sink");
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut command)
+        .add_subcommand("play", "Play a sound", play);
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    let args = vec!["./argparse_test".to_string(), "play".to_string(),
+                    "--input".to_string()];
+    assert_eq!(ap.parse(args, &mut stdout, &mut stderr), Err(2));
+    assert_eq!("Usage:\n".to_string()
+        + "  ./argparse_test play [OPTIONS]\n"
+        + "./argparse_test play: Unknown option --input\n",
+        from_utf8(&stderr[..]).unwrap());
+}
+
+#[test]
+fn test_help() {
+    let mut command = String::new();
+    let mut play = ArgumentParser::new();
+    play.set_description("Plays a sound");
+    let mut ap = ArgumentParser::new();
+    ap.set_description("Test program");
+    ap.refer(&mut command)
+        .add_subcommand("play", "Play a sound", play)
+        .add_subcommand("record", "Record a sound", ArgumentParser::new())
+        .required();
+    let mut buf = Vec::<u8>::new();
+    assert!(ap.print_help("./argparse_test", &mut buf).is_ok());
+    assert_eq!("Usage:\n".to_string()
+        + "  ./argparse_test COMMAND [...]\n"
+        + "\n"
+        + "Test program\n"
+        + "\n"
+        + "Commands:\n"
+        + "  play                  Play a sound\n"
+        + "  record                Record a sound\n"
+        + "\n"
+        + "Optional arguments:\n"
+        + "  -h,--help             Show this help message and exit\n"
+        , from_utf8(&buf[..]).unwrap().to_string());
+}
+
+#[test]
+fn test_subcommand_help() {
+    let mut command = String::new();
+    let mut play = ArgumentParser::new();
+    play.set_description("Plays a sound");
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut command)
+        .add_subcommand("play", "Play a sound", play);
+    let mut stdout = Vec::<u8>::new();
+    let mut stderr = Vec::<u8>::new();
+    let args = vec!["./argparse_test".to_string(), "play".to_string(),
+                    "--help".to_string()];
+    assert_eq!(ap.parse(args, &mut stdout, &mut stderr), Err(0));
+    assert_eq!("Usage:\n".to_string()
+        + "  ./argparse_test play\n"
+        + "\n"
+        + "Plays a sound\n"
+        + "\n"
+        + "Optional arguments:\n"
+        + "  -h,--help             Show this help message and exit\n"
+        , from_utf8(&stdout[..]).unwrap().to_string());
+}
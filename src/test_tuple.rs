@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use config::Config;
+use super::{Parse, ParseCollect};
+use test_parser::{check_ok, check_result};
+
+fn size(args: &[&str]) -> Result<(u32, u32), String> {
+    let mut size = (0, 0);
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut size)
+          .add_option(&["-s", "--size"], Parse, "Size")
+          .metavar("W H");
+        try!(check_result(&ap, args));
+    }
+    return Ok(size);
+}
+
+#[test]
+fn test_pair() {
+    assert_eq!(size(&["./argparse_test"]), Ok((0, 0)));
+    assert_eq!(size(&["./argparse_test", "--size", "640", "480"]),
+        Ok((640, 480)));
+    assert_eq!(size(&["./argparse_test", "-s640", "480"]), Ok((640, 480)));
+    assert_eq!(size(&["./argparse_test", "-s", "1", "2", "-s", "3", "4"]),
+        Ok((3, 4)));
+}
+
+#[test]
+fn test_pair_errors() {
+    assert_eq!(size(&["./argparse_test", "--size", "640"]),
+        Err("./argparse_test: --size requires 2 values, got 1".to_string()));
+    assert_eq!(size(&["./argparse_test", "--size", "640", "x"]),
+        Err("./argparse_test: Bad value \"x\": \
+             ParseIntError { kind: InvalidDigit }".to_string()));
+}
+
+#[test]
+fn test_heterogeneous() {
+    let mut val = (String::new(), 0u16, PathBuf::new());
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut val)
+          .add_argument("mount", Parse, "Mount");
+        check_ok(&ap, &["./argparse_test", "data", "7", "/mnt"]);
+    }
+    assert_eq!(val, ("data".to_string(), 7, PathBuf::from("/mnt")));
+}
+
+fn maps(args: &[&str]) -> Vec<(PathBuf, PathBuf)> {
+    let mut maps = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut maps)
+          .add_option(&["-m", "--map"], ParseCollect, "Map")
+          .metavar("SRC DST");
+        check_ok(&ap, args);
+    }
+    return maps;
+}
+
+#[test]
+fn test_collect() {
+    assert_eq!(maps(&["./argparse_test"]), vec!());
+    assert_eq!(maps(&["./argparse_test", "--map", "a", "b", "-m", "c", "d"]),
+        vec!((PathBuf::from("a"), PathBuf::from("b")),
+             (PathBuf::from("c"), PathBuf::from("d"))));
+}
+
+fn points(args: &[&str]) -> Result<Vec<(u32, u32)>, String> {
+    let mut points = vec!();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.refer(&mut points)
+          .add_argument("points", ParseCollect, "Points");
+        try!(check_result(&ap, args));
+    }
+    return Ok(points);
+}
+
+#[test]
+fn test_collect_argument() {
+    assert_eq!(points(&["./argparse_test"]), Ok(vec!()));
+    assert_eq!(points(&["./argparse_test", "1", "2"]), Ok(vec!((1, 2))));
+    assert_eq!(points(&["./argparse_test", "1", "2", "3", "4", "5", "6"]),
+        Ok(vec!((1, 2), (3, 4), (5, 6))));
+    assert_eq!(points(&["./argparse_test", "1", "2", "3"]),
+        Err("./argparse_test: points requires 2 values, got 3".to_string()));
+}
+
+#[test]
+fn test_config() {
+    let config = Config::parse("points = 1,2,3\n").unwrap();
+    let mut points: Vec<(u32, u32)> = vec!();
+    let mut stderr = Vec::<u8>::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_config(&config);
+        ap.refer(&mut points)
+          .add_option(&["--point"], ParseCollect, "Point")
+          .delimiter(',')
+          .config_key("points", ParseCollect);
+        ap.parse(vec!["./argparse_test".to_string()],
+                 &mut Vec::new(), &mut stderr).unwrap();
+    }
+    assert_eq!(points, vec!());
+    assert_eq!(from_utf8(&stderr[..]).unwrap(),
+        "WARNING: Config <config>:1: points: \
+         points requires 2 values, got 1\n");
+}
+
+#[test]
+fn test_help() {
+    let mut size = (0u32, 0u32);
+    let mut maps: Vec<(String, String)> = vec!();
+    let mut points: Vec<(u32, u32)> = vec!();
+    let mut ap = ArgumentParser::new();
+    ap.refer(&mut size)
+      .add_option(&["--size"], Parse, "Size");
+    ap.refer(&mut maps)
+      .add_option(&["--map"], ParseCollect, "Map")
+      .metavar("SRC DST");
+    ap.refer(&mut points)
+      .add_argument("points", ParseCollect, "Points");
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    assert_eq!(from_utf8(&buf[..]).unwrap(), "Usage:
+  ./argparse_test [OPTIONS] [POINTS POINTS ...]
+
+
+Positional arguments:
+  points                Points
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  --size SIZE SIZE      Size
+  --map SRC DST         Map
+");
+}
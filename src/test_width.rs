@@ -0,0 +1,110 @@
+use std::str::from_utf8;
+
+use parser::ArgumentParser;
+use terminal::parse_columns;
+use super::{Store, StoreTrue};
+
+fn help(width: Option<usize>, option_width: Option<usize>, long: bool)
+    -> String
+{
+    let mut verbose = false;
+    let mut value = 0;
+    let mut ap = ArgumentParser::new();
+    match width {
+        Some(width) => ap.set_width(width),
+        None => {}
+    }
+    match option_width {
+        Some(width) => ap.set_option_width(width),
+        None => {}
+    }
+    ap.set_description("Test program with a description that is long \
+        enough to be wrapped");
+    ap.refer(&mut verbose)
+        .add_option(&["-v", "--verbose"], StoreTrue,
+        "Show more details about what the program does");
+    if long {
+        ap.refer(&mut value)
+            .add_option(&["--very-long-option-name"], Store,
+            "Set value")
+            .metavar("N");
+    }
+    let mut buf = Vec::<u8>::new();
+    ap.print_help("./argparse_test", &mut buf).unwrap();
+    return from_utf8(&buf[..]).unwrap().to_string();
+}
+
+#[test]
+fn test_default() {
+    assert_eq!(help(None, None, false), "Usage:
+  ./argparse_test [OPTIONS]
+
+Test program with a description that is long enough to be wrapped
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  -v,--verbose          Show more details about what the program does
+");
+}
+
+#[test]
+fn test_narrow() {
+    assert_eq!(help(Some(50), None, false), "Usage:
+  ./argparse_test [OPTIONS]
+
+Test program with a description that is long
+enough to be wrapped
+
+Optional arguments:
+  -h,--help             Show this help message and
+                        exit
+  -v,--verbose          Show more details about
+                        what the program does
+");
+}
+
+#[test]
+fn test_adaptive_column() {
+    assert_eq!(help(Some(120), None, true), "Usage:
+  ./argparse_test [OPTIONS]
+
+Test program with a description that is long enough to be wrapped
+
+Optional arguments:
+  -h,--help                  Show this help message and exit
+  -v,--verbose               Show more details about what the program does
+  --very-long-option-name N  Set value
+");
+    assert_eq!(help(None, None, true), "Usage:
+  ./argparse_test [OPTIONS]
+
+Test program with a description that is long enough to be wrapped
+
+Optional arguments:
+  -h,--help             Show this help message and exit
+  -v,--verbose          Show more details about what the program does
+  --very-long-option-name N
+                        Set value
+");
+}
+
+#[test]
+fn test_option_width() {
+    assert_eq!(help(None, Some(16), false), "Usage:
+  ./argparse_test [OPTIONS]
+
+Test program with a description that is long enough to be wrapped
+
+Optional arguments:
+  -h,--help     Show this help message and exit
+  -v,--verbose  Show more details about what the program does
+");
+}
+
+#[test]
+fn test_columns() {
+    assert_eq!(parse_columns("120"), Some(120));
+    assert_eq!(parse_columns(" 80\n"), Some(80));
+    assert_eq!(parse_columns("0"), None);
+    assert_eq!(parse_columns("wide"), None);
+}
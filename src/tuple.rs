@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::rc::Rc;
+
+use super::{Parse, ParseCollect, FromCommandLine};
+use super::action::{Action, Arity};
+use super::action::{TypedAction, IArgsAction};
+use super::action::ParseResult;
+use super::action::ParseResult::{Parsed, BadValue, WrongNumber};
+use super::action::Action::{Push, Many};
+
+pub struct TupleAction<'a, T: 'a> {
+    cell: Rc<RefCell<&'a mut T>>,
+}
+
+pub struct TupleListAction<'a, T: 'a> {
+    cell: Rc<RefCell<&'a mut Vec<T>>>,
+}
+
+fn parse_value<T: FromCommandLine>(arg: &OsStr) -> Result<T, ParseResult> {
+    return FromCommandLine::from_os_argument(arg).map_err(|error| {
+        BadValue(arg.to_string_lossy().into_owned(), Some(error))
+    });
+}
+
+/// The parser checks the number of values itself, this is a fallback
+fn wrong_number(num: usize, got: usize) -> ParseResult {
+    return WrongNumber(Arity::from(num), got);
+}
+
+macro_rules! value {
+    ($arg:expr) => {
+        match parse_value($arg) {
+            Ok(x) => x,
+            Err(res) => return res,
+        }
+    }
+}
+
+macro_rules! tuple_impls {
+    ($num:expr; $($name:ident $idx:tt),+) => {
+        impl<$($name),+> TypedAction<($($name,)+)> for Parse
+            where $($name: 'static + FromCommandLine),+
+        {
+            fn bind<'x>(&self, cell: Rc<RefCell<&'x mut ($($name,)+)>>)
+                -> Action<'x>
+            {
+                return Many(Box::new(TupleAction { cell: cell }));
+            }
+            fn arity(&self) -> Option<Arity> {
+                return Some(Arity::from($num));
+            }
+        }
+
+        impl<$($name),+> TypedAction<Vec<($($name,)+)>> for ParseCollect
+            where $($name: 'static + FromCommandLine),+
+        {
+            fn bind<'x>(&self, cell: Rc<RefCell<&'x mut Vec<($($name,)+)>>>)
+                -> Action<'x>
+            {
+                return Push(Box::new(TupleListAction { cell: cell }));
+            }
+            fn arity(&self) -> Option<Arity> {
+                return Some(Arity::from($num));
+            }
+        }
+
+        impl<'a, $($name: FromCommandLine),+> IArgsAction
+            for TupleAction<'a, ($($name,)+)>
+        {
+            fn parse_args(&self, args: &[&str]) -> ParseResult {
+                let args: Vec<_> = args.iter().map(OsStr::new).collect();
+                return self.parse_os_args(&args[..]);
+            }
+            fn parse_os_args(&self, args: &[&OsStr]) -> ParseResult {
+                if args.len() < $num {
+                    return wrong_number($num, args.len());
+                }
+                // the option may be repeated, the last one wins
+                let args = &args[args.len() - $num..];
+                **self.cell.borrow_mut() = ($(value!(args[$idx]),)+);
+                return Parsed;
+            }
+        }
+
+        impl<'a, $($name: FromCommandLine),+> IArgsAction
+            for TupleListAction<'a, ($($name,)+)>
+        {
+            fn parse_args(&self, args: &[&str]) -> ParseResult {
+                let args: Vec<_> = args.iter().map(OsStr::new).collect();
+                return self.parse_os_args(&args[..]);
+            }
+            fn parse_os_args(&self, args: &[&OsStr]) -> ParseResult {
+                if args.len() % $num != 0 {
+                    return wrong_number($num, args.len() % $num);
+                }
+                let mut result = vec!();
+                for args in args.chunks($num) {
+                    result.push(($(value!(args[$idx]),)+));
+                }
+                **self.cell.borrow_mut() = result;
+                return Parsed;
+            }
+        }
+    }
+}
+
+tuple_impls!(2; A 0, B 1);
+tuple_impls!(3; A 0, B 1, C 2);
+tuple_impls!(4; A 0, B 1, C 2, D 3);